@@ -1,22 +1,34 @@
 pub mod parsers;
 
 use core::{
+    cmp::Ordering,
     fmt::{self, Display},
+    hash::{Hash, Hasher},
     iter::ExactSizeIterator,
+    str::FromStr,
     write,
 };
 
-use crate::useflag::UseDep;
+use std::collections::HashSet;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+use crate::{
+    error::Error,
+    parser_utils::parse_all,
+    useflag::{Operator, Sign, UseDep, UseFlag},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Blocker {
     Weak,
     Strong,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VersionOperator {
     Eq,
+    EqGlob,
     Lt,
     LtEq,
     Gt,
@@ -24,28 +36,53 @@ pub enum VersionOperator {
     Roughly,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
 pub struct Category(String);
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
 pub struct Name(String);
 
 #[derive(Clone, Debug)]
 pub struct VersionNumber(String);
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct Repo(String);
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SlotOperator {
     Eq,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
 pub struct Slot {
-    primary: String,
+    primary: Option<String>,
     sub: Option<String>,
     operator: Option<SlotOperator>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VersionSuffixKind {
     Alpha,
     Beta,
@@ -61,6 +98,11 @@ pub struct VersionSuffix {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
 pub struct Version {
     numbers: Vec<VersionNumber>,
     letter: Option<char>,
@@ -68,7 +110,36 @@ pub struct Version {
     revision: Option<VersionNumber>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct Cpv {
+    category: Category,
+    name: Name,
+    version: Version,
+}
+
 #[derive(Clone, Debug)]
+pub struct Package {
+    pub category: Category,
+    pub name: Name,
+    pub version: Version,
+    pub slot: String,
+    pub subslot: Option<String>,
+    pub repo: Option<Repo>,
+    pub iuse: HashSet<UseFlag>,
+    pub useflags: HashSet<UseFlag>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
 pub struct Atom {
     blocker: Option<Blocker>,
     version_operator: Option<VersionOperator>,
@@ -76,6 +147,7 @@ pub struct Atom {
     name: Name,
     version: Option<Version>,
     slot: Option<Slot>,
+    repo: Option<Repo>,
     usedeps: Vec<UseDep>,
 }
 
@@ -97,6 +169,26 @@ impl VersionNumber {
     }
 }
 
+impl Repo {
+    pub fn get(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Slot {
+    pub fn primary(&self) -> Option<&str> {
+        self.primary.as_deref()
+    }
+
+    pub fn sub(&self) -> Option<&str> {
+        self.sub.as_deref()
+    }
+
+    pub fn operator(&self) -> Option<&SlotOperator> {
+        self.operator.as_ref()
+    }
+}
+
 impl VersionSuffix {
     pub fn kind(&self) -> VersionSuffixKind {
         self.kind
@@ -123,6 +215,196 @@ impl Version {
     pub fn revision(&self) -> Option<&VersionNumber> {
         self.revision.as_ref()
     }
+
+    // PMS 8.3.1, only the components present in the glob are compared, so
+    // 1.2* matches 1.2 and 1.2.3 but not 1.20
+    fn matches_glob(&self, glob: &Self) -> bool {
+        let numbers = glob.numbers.len();
+
+        if self.numbers.len() < numbers
+            || cmp_version_numbers(&self.numbers[..numbers], &glob.numbers).is_ne()
+        {
+            return false;
+        }
+
+        if glob.letter.is_none() && glob.suffixes.is_empty() && glob.revision.is_none() {
+            return true;
+        }
+
+        if self.numbers.len() != numbers || (glob.letter.is_some() && self.letter != glob.letter) {
+            return false;
+        }
+
+        if glob.suffixes.is_empty() && glob.revision.is_none() {
+            return true;
+        }
+
+        let suffixes = glob.suffixes.len();
+
+        if self.letter != glob.letter || self.suffixes.len() < suffixes {
+            return false;
+        }
+
+        if glob.revision.is_some() {
+            return self == glob;
+        }
+
+        // the last suffix in the glob is a prefix too, so _p* matches _p1
+        match glob.suffixes.split_last() {
+            Some((last, rest)) => {
+                self.suffixes[..rest.len()] == *rest
+                    && self.suffixes[rest.len()].kind == last.kind
+                    && (last.number.is_none() || self.suffixes[rest.len()] == *last)
+            }
+            None => true,
+        }
+    }
+
+    fn cmp_without_revision(&self, other: &Self) -> Ordering {
+        cmp_version_numbers(&self.numbers, &other.numbers)
+            .then_with(|| self.letter.cmp(&other.letter))
+            .then_with(|| cmp_version_suffixes(&self.suffixes, &other.suffixes))
+    }
+}
+
+impl PartialEq for VersionNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for VersionNumber {}
+
+impl PartialOrd for VersionNumber {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for VersionNumber {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_numeric(self.get(), other.get())
+    }
+}
+
+impl Hash for VersionNumber {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.trim_start_matches('0').hash(state);
+    }
+}
+
+impl PartialEq for VersionSuffix {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for VersionSuffix {}
+
+impl PartialOrd for VersionSuffix {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// PMS algorithm 3.6, a missing suffix number is equivalent to 0
+impl Ord for VersionSuffix {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.kind.cmp(&other.kind).then_with(|| {
+            cmp_numeric(
+                self.number.as_ref().map_or("0", VersionNumber::get),
+                other.number.as_ref().map_or("0", VersionNumber::get),
+            )
+        })
+    }
+}
+
+impl Hash for VersionSuffix {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+        self.number
+            .as_ref()
+            .map_or("", |number| number.0.trim_start_matches('0'))
+            .hash(state);
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// PMS algorithm 3.1
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_without_revision(other).then_with(|| {
+            cmp_numeric(
+                self.revision.as_ref().map_or("0", VersionNumber::get),
+                other.revision.as_ref().map_or("0", VersionNumber::get),
+            )
+        })
+    }
+}
+
+// must agree with the equivalence classes of `Ord`, so components after the
+// first are hashed the same way algorithm 3.3 compares them
+impl Hash for Version {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.numbers.len().hash(state);
+
+        for (i, number) in self.numbers.iter().enumerate() {
+            if i > 0 && number.0.starts_with('0') {
+                number.0.trim_end_matches('0').hash(state);
+            } else {
+                number.hash(state);
+            }
+        }
+
+        self.letter.hash(state);
+        self.suffixes.hash(state);
+        self.revision
+            .as_ref()
+            .map_or("", |revision| revision.0.trim_start_matches('0'))
+            .hash(state);
+    }
+}
+
+impl Cpv {
+    pub fn category(&self) -> &Category {
+        &self.category
+    }
+
+    pub fn name(&self) -> &Name {
+        &self.name
+    }
+
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+}
+
+impl From<Cpv> for Atom {
+    fn from(cpv: Cpv) -> Self {
+        Self {
+            blocker: None,
+            version_operator: Some(VersionOperator::Eq),
+            category: cpv.category,
+            name: cpv.name,
+            version: Some(cpv.version),
+            slot: None,
+            repo: None,
+            usedeps: Vec::new(),
+        }
+    }
 }
 
 impl Atom {
@@ -150,9 +432,77 @@ impl Atom {
         self.slot.as_ref()
     }
 
+    pub fn repo(&self) -> Option<&Repo> {
+        self.repo.as_ref()
+    }
+
     pub fn usedeps(&self) -> impl ExactSizeIterator<Item = &UseDep> {
         self.usedeps.iter()
     }
+
+    // the order of usedeps has no meaning, so this is used to put them in a
+    // canonical order when normalizing dependencies
+    pub(crate) fn sort_usedeps(&mut self) {
+        self.usedeps
+            .sort_by(|a, b| a.useflag().cmp(b.useflag()).then_with(|| a.cmp(b)));
+        self.usedeps.dedup();
+    }
+
+    // conditional usedeps (`foo=`, `foo?` and their negations) depend on the
+    // USE flags of the package that owns the dependency, so they are ignored
+    // here, use `matches_with_parent` to take them into account
+    pub fn matches(&self, package: &Package) -> bool {
+        self.matches_impl(package, None)
+    }
+
+    pub fn matches_with_parent(&self, package: &Package, parent: &HashSet<UseFlag>) -> bool {
+        self.matches_impl(package, Some(parent))
+    }
+
+    fn matches_impl(&self, package: &Package, parent: Option<&HashSet<UseFlag>>) -> bool {
+        self.category == package.category
+            && self.name == package.name
+            && self.matches_version(&package.version)
+            && self.matches_slot(package)
+            && self
+                .repo
+                .as_ref()
+                .is_none_or(|repo| package.repo.as_ref() == Some(repo))
+            && self
+                .usedeps
+                .iter()
+                .all(|usedep| usedep_matches(usedep, package, parent))
+    }
+
+    fn matches_version(&self, version: &Version) -> bool {
+        let Some(expected) = self.version.as_ref() else {
+            return true;
+        };
+
+        match self.version_operator.unwrap_or(VersionOperator::Eq) {
+            VersionOperator::Eq => version == expected,
+            VersionOperator::EqGlob => version.matches_glob(expected),
+            VersionOperator::Lt => version < expected,
+            VersionOperator::LtEq => version <= expected,
+            VersionOperator::Gt => version > expected,
+            VersionOperator::GtEq => version >= expected,
+            VersionOperator::Roughly => version.cmp_without_revision(expected).is_eq(),
+        }
+    }
+
+    fn matches_slot(&self, package: &Package) -> bool {
+        let Some(slot) = self.slot.as_ref() else {
+            return true;
+        };
+
+        // a package without an explicit subslot has a subslot equal to its slot
+        let subslot = package.subslot.as_deref().unwrap_or(package.slot.as_str());
+
+        slot.primary
+            .as_ref()
+            .is_none_or(|primary| primary == "*" || *primary == package.slot)
+            && slot.sub.as_ref().is_none_or(|sub| sub == subslot)
+    }
 }
 
 impl Display for Blocker {
@@ -167,7 +517,7 @@ impl Display for Blocker {
 impl Display for VersionOperator {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
-            Self::Eq => write!(f, "="),
+            Self::Eq | Self::EqGlob => write!(f, "="),
             Self::Lt => write!(f, "<"),
             Self::Gt => write!(f, ">"),
             Self::LtEq => write!(f, "<="),
@@ -189,6 +539,12 @@ impl Display for Name {
     }
 }
 
+impl Display for Repo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl Display for VersionNumber {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.0)
@@ -205,7 +561,9 @@ impl Display for SlotOperator {
 
 impl Display for Slot {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{}", self.primary)?;
+        if let Some(primary) = self.primary.as_ref() {
+            write!(f, "{}", primary)?;
+        }
 
         if let Some(subslot) = self.sub.as_ref() {
             write!(f, "/{}", subslot)?;
@@ -264,6 +622,12 @@ impl Display for Version {
     }
 }
 
+impl Display for Cpv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}-{}", self.category, self.name, self.version)
+    }
+}
+
 impl Display for Atom {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Some(blocker) = self.blocker {
@@ -280,10 +644,18 @@ impl Display for Atom {
             write!(f, "-{}", version)?;
         }
 
+        if let Some(VersionOperator::EqGlob) = self.version_operator {
+            write!(f, "*")?;
+        }
+
         if let Some(slot) = self.slot.as_ref() {
             write!(f, ":{}", slot)?;
         }
 
+        if let Some(repo) = self.repo.as_ref() {
+            write!(f, "::{}", repo)?;
+        }
+
         if !self.usedeps.is_empty() {
             write!(f, "[")?;
             intersperse(self.usedeps.iter(), ",", f)?;
@@ -294,6 +666,141 @@ impl Display for Atom {
     }
 }
 
+impl FromStr for Category {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::category)
+    }
+}
+
+impl FromStr for Name {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::name)
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::version)
+    }
+}
+
+impl FromStr for Slot {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::slot)
+    }
+}
+
+impl FromStr for Repo {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::repo)
+    }
+}
+
+impl FromStr for Cpv {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::cpv)
+    }
+}
+
+impl FromStr for Atom {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::atom)
+    }
+}
+
+// PMS 8.3.4
+fn usedep_matches(usedep: &UseDep, package: &Package, parent: Option<&HashSet<UseFlag>>) -> bool {
+    let flag = usedep.useflag();
+
+    let enabled = if package.iuse.contains(flag) || package.useflags.contains(flag) {
+        package.useflags.contains(flag)
+    } else {
+        match usedep.sign() {
+            Some(Sign::Plus) => true,
+            Some(Sign::Minus) => false,
+            None => return false,
+        }
+    };
+
+    let negated = usedep.negate().is_some();
+
+    match (usedep.operator(), parent) {
+        (None, _) => enabled != negated,
+        (Some(_), None) => true,
+        (Some(Operator::Equal), Some(parent)) => enabled == (parent.contains(flag) != negated),
+        (Some(Operator::Question), Some(parent)) => match (parent.contains(flag), negated) {
+            (true, false) => enabled,
+            (false, true) => !enabled,
+            _ => true,
+        },
+    }
+}
+
+// compares two strings of ascii digits by their integer value, without
+// limiting how many digits they may have
+fn cmp_numeric(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+// PMS algorithms 3.2 and 3.3
+fn cmp_version_numbers(a: &[VersionNumber], b: &[VersionNumber]) -> Ordering {
+    let first = a[0].cmp(&b[0]);
+
+    if first.is_ne() {
+        return first;
+    }
+
+    for (a, b) in a.iter().zip(b).skip(1) {
+        let ordering = if a.0.starts_with('0') || b.0.starts_with('0') {
+            a.0.trim_end_matches('0').cmp(b.0.trim_end_matches('0'))
+        } else {
+            a.cmp(b)
+        };
+
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+
+    a.len().cmp(&b.len())
+}
+
+// PMS algorithm 3.5
+fn cmp_version_suffixes(a: &[VersionSuffix], b: &[VersionSuffix]) -> Ordering {
+    for (a, b) in a.iter().zip(b) {
+        let ordering = a.cmp(b);
+
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+
+    match a.len().cmp(&b.len()) {
+        Ordering::Greater if matches!(a[b.len()].kind, VersionSuffixKind::P) => Ordering::Greater,
+        Ordering::Greater => Ordering::Less,
+        Ordering::Less if matches!(b[a.len()].kind, VersionSuffixKind::P) => Ordering::Less,
+        Ordering::Less => Ordering::Greater,
+        Ordering::Equal => Ordering::Equal,
+    }
+}
+
 fn intersperse(
     iter: impl ExactSizeIterator<Item = impl Display>,
     separator: &str,
@@ -311,3 +818,315 @@ fn intersperse(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+
+    use core::cmp::Ordering;
+    use std::{collections::hash_map::DefaultHasher, hash::Hasher};
+
+    use nom::{combinator::eof, sequence::terminated, Parser};
+
+    use crate::test_utils::useflags;
+
+    use super::*;
+
+    fn atom(input: &str) -> Atom {
+        terminated(parsers::atom, eof)
+            .parse_complete(input)
+            .unwrap()
+            .1
+    }
+
+    fn package(category: &str, name: &str, version: &str, slot: &str) -> Package {
+        let (slot, subslot) = match slot.split_once('/') {
+            Some((slot, subslot)) => (slot, Some(subslot.to_string())),
+            None => (slot, None),
+        };
+
+        Package {
+            category: Category(category.to_string()),
+            name: Name(name.to_string()),
+            version: self::version(version),
+            slot: slot.to_string(),
+            subslot,
+            repo: None,
+            iuse: HashSet::new(),
+            useflags: HashSet::new(),
+        }
+    }
+
+    fn version(input: &str) -> Version {
+        terminated(parsers::version, eof)
+            .parse_complete(input)
+            .unwrap()
+            .1
+    }
+
+    fn hash(version: &Version) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        version.hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_version_cmp() {
+        let table = [
+            // algorithm 3.2, the first component is compared as an integer
+            ("1", Ordering::Equal, "1"),
+            ("1", Ordering::Less, "2"),
+            ("01", Ordering::Equal, "1"),
+            ("10", Ordering::Greater, "9"),
+            ("1.0", Ordering::Greater, "1"),
+            ("1.0.1", Ordering::Greater, "1.0"),
+            ("12.2.5", Ordering::Greater, "12.2b"),
+            // algorithm 3.3, leading zeros switch to string comparison
+            ("1.2", Ordering::Less, "1.10"),
+            ("1.0.9", Ordering::Less, "1.0.10"),
+            ("1.01", Ordering::Less, "1.1"),
+            ("1.01", Ordering::Greater, "1.001"),
+            ("1.010", Ordering::Equal, "1.01"),
+            ("1.0", Ordering::Equal, "1.00"),
+            (
+                "1.99999999999999999999",
+                Ordering::Less,
+                "1.100000000000000000000",
+            ),
+            // algorithm 3.4
+            ("1.0a", Ordering::Greater, "1.0"),
+            ("1.0a", Ordering::Less, "1.0b"),
+            ("1.0z", Ordering::Less, "1.0.1"),
+            // algorithms 3.5 and 3.6
+            ("1.0_alpha", Ordering::Less, "1.0_beta"),
+            ("1.0_beta", Ordering::Less, "1.0_pre"),
+            ("1.0_pre", Ordering::Less, "1.0_rc"),
+            ("1.0_rc", Ordering::Less, "1.0"),
+            ("1.0", Ordering::Less, "1.0_p"),
+            ("1.0_alpha1", Ordering::Greater, "1.0_alpha"),
+            ("1.0_alpha", Ordering::Equal, "1.0_alpha0"),
+            ("1.0_alpha2", Ordering::Less, "1.0_alpha10"),
+            ("1.0_p1_alpha", Ordering::Less, "1.0_p1"),
+            ("1.0_p1_p1", Ordering::Greater, "1.0_p1"),
+            ("1.0_alpha_beta", Ordering::Greater, "1.0_alpha_alpha"),
+            ("1.0_p20250101", Ordering::Less, "1.0_p20250128"),
+            // algorithm 3.7
+            ("1.0-r1", Ordering::Greater, "1.0"),
+            ("1.0-r0", Ordering::Equal, "1.0"),
+            ("1.0-r01", Ordering::Equal, "1.0-r1"),
+            ("1.0-r2", Ordering::Less, "1.0-r10"),
+            ("1.0_p1", Ordering::Greater, "1.0-r1"),
+            ("1.0_rc1-r1", Ordering::Less, "1.0"),
+        ];
+
+        for (a, ordering, b) in table {
+            let (a, b) = (version(a), version(b));
+
+            assert_eq!(a.cmp(&b), ordering, "{a} {b}");
+
+            assert_eq!(b.cmp(&a), ordering.reverse(), "{b} {a}");
+
+            if ordering.is_eq() {
+                assert_eq!(hash(&a), hash(&b), "{a} {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_version_sort() {
+        let mut versions = [
+            "1.0_p1",
+            "1.0-r1",
+            "1.0",
+            "0.9",
+            "1.0_rc2",
+            "1.0a",
+            "1.0_alpha",
+            "1.0.1",
+        ]
+        .map(version);
+
+        versions.sort();
+
+        assert_eq!(
+            versions.map(|version| version.to_string()),
+            [
+                "0.9",
+                "1.0_alpha",
+                "1.0_rc2",
+                "1.0",
+                "1.0-r1",
+                "1.0_p1",
+                "1.0a",
+                "1.0.1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_atom_matches_version() {
+        let package = package("dev-lang", "rust", "1.80.1-r1", "stable");
+
+        let table = [
+            ("dev-lang/rust", true),
+            ("dev-lang/python", false),
+            ("dev-util/rust", false),
+            ("=dev-lang/rust-1.80.1-r1", true),
+            ("=dev-lang/rust-1.80.1", false),
+            ("~dev-lang/rust-1.80.1", true),
+            ("~dev-lang/rust-1.80.1-r5", true),
+            ("~dev-lang/rust-1.80", false),
+            (">=dev-lang/rust-1.80", true),
+            (">=dev-lang/rust-1.80.1-r1", true),
+            (">dev-lang/rust-1.80.1-r1", false),
+            ("<dev-lang/rust-1.81", true),
+            ("<dev-lang/rust-1.80.1_rc1", false),
+            ("<=dev-lang/rust-1.80.1-r1", true),
+            ("!!<dev-lang/rust-1.81", true),
+            ("=dev-lang/rust-1*", true),
+            ("=dev-lang/rust-1.8*", false),
+            ("=dev-lang/rust-1.80*", true),
+            ("=dev-lang/rust-1.80.1*", true),
+            ("=dev-lang/rust-1.80.1-r1*", true),
+            ("=dev-lang/rust-1.80.1-r2*", false),
+            ("=dev-lang/rust-1.80.1_p*", false),
+        ];
+
+        for (input, expected) in table {
+            assert_eq!(atom(input).matches(&package), expected, "{input}");
+        }
+
+        let table = [
+            ("1.0_p1", "=cat/p-1.0_p*", true),
+            ("1.0_p1", "=cat/p-1.0_p1*", true),
+            ("1.0_p1", "=cat/p-1.0_p2*", false),
+            ("1.0_p1", "=cat/p-1.0_rc*", false),
+            ("1.0", "=cat/p-1.0_p*", false),
+            ("1.0_p1-r3", "=cat/p-1.0_p*", true),
+            ("1.0_p1-r3", "=cat/p-1.0_p1-r3*", true),
+            ("1.0_p1_beta2", "=cat/p-1.0_p1_beta*", true),
+            ("1.0_p2_beta2", "=cat/p-1.0_p1_beta*", false),
+            ("3.13.0_beta1", "=cat/p-3.13.0_beta*", true),
+            ("3.13.0_beta4", "=cat/p-3.13.0_beta*", true),
+            ("3.13.0_rc1", "=cat/p-3.13.0_beta*", false),
+            ("3.13.0", "=cat/p-3.13.0_beta*", false),
+        ];
+
+        for (version, input, expected) in table {
+            let package = self::package("cat", "p", version, "0");
+
+            assert_eq!(atom(input).matches(&package), expected, "{input} {version}");
+        }
+    }
+
+    #[test]
+    fn test_atom_matches_slot() {
+        let package = package("dev-libs", "openssl", "3.3.2", "0/3");
+
+        let table = [
+            ("dev-libs/openssl:0", true),
+            ("dev-libs/openssl:1", false),
+            ("dev-libs/openssl:0/3", true),
+            ("dev-libs/openssl:0/1.1", false),
+            ("dev-libs/openssl:0=", true),
+            ("dev-libs/openssl:*", true),
+        ];
+
+        for (input, expected) in table {
+            assert_eq!(atom(input).matches(&package), expected, "{input}");
+        }
+
+        let package = self::package("dev-libs", "openssl", "3.3.2", "0");
+
+        assert!(atom("dev-libs/openssl:0/0").matches(&package));
+    }
+
+    #[test]
+    fn test_atom_matches_repo() {
+        let mut package = package("dev-libs", "openssl", "3.3.2", "0/3");
+
+        assert!(atom("dev-libs/openssl").matches(&package));
+
+        assert!(!atom("dev-libs/openssl::gentoo").matches(&package));
+
+        package.repo = Some(Repo(String::from("gentoo")));
+
+        assert!(atom("dev-libs/openssl:0::gentoo").matches(&package));
+
+        assert!(!atom("dev-libs/openssl::guru").matches(&package));
+    }
+
+    #[test]
+    fn test_atom_matches_usedeps() {
+        let mut package = package("dev-lang", "rust", "1.80.1", "stable");
+
+        package.iuse = useflags(&["llvm", "wasm", "doc"]);
+        package.useflags = useflags(&["llvm"]);
+
+        let table = [
+            ("dev-lang/rust[llvm]", true),
+            ("dev-lang/rust[-llvm]", false),
+            ("dev-lang/rust[wasm]", false),
+            ("dev-lang/rust[-wasm,llvm]", true),
+            ("dev-lang/rust[missing]", false),
+            ("dev-lang/rust[missing(+)]", true),
+            ("dev-lang/rust[missing(-)]", false),
+            ("dev-lang/rust[-missing(-)]", true),
+            ("dev-lang/rust[wasm=,doc?]", true),
+        ];
+
+        for (input, expected) in table {
+            assert_eq!(atom(input).matches(&package), expected, "{input}");
+        }
+
+        let parent = useflags(&["wasm", "llvm"]);
+
+        let table = [
+            ("dev-lang/rust[llvm=]", true),
+            ("dev-lang/rust[wasm=]", false),
+            ("dev-lang/rust[!doc=]", false),
+            ("dev-lang/rust[!wasm=]", true),
+            ("dev-lang/rust[!llvm=]", false),
+            ("dev-lang/rust[wasm?]", false),
+            ("dev-lang/rust[doc?]", true),
+            ("dev-lang/rust[!doc?]", true),
+            ("dev-lang/rust[!wasm?]", true),
+        ];
+
+        for (input, expected) in table {
+            assert_eq!(
+                atom(input).matches_with_parent(&package, &parent),
+                expected,
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("dev-lang/rust".parse::<Category>().unwrap_err().offset(), 8);
+
+        assert!("rust-1.0".parse::<Name>().is_err());
+
+        assert!("1.0_rc1-r1".parse::<Version>().is_ok());
+
+        assert!("1.0 ".parse::<Version>().is_err());
+
+        assert!("0/1.2=".parse::<Slot>().is_ok());
+
+        assert!("sys-apps/portage-3.0.66".parse::<Cpv>().is_ok());
+
+        let error = ">=dev-lang/rust-1.80[llvm] trailing"
+            .parse::<Atom>()
+            .unwrap_err();
+
+        assert_eq!(error.offset(), 26);
+
+        assert_eq!(error.expected(), Some(crate::error::Expected::Eof));
+
+        let atom = ">=dev-lang/rust-1.80:stable[llvm]".parse::<Atom>().unwrap();
+
+        assert_eq!(atom.to_string(), ">=dev-lang/rust-1.80:stable[llvm]");
+    }
+}
@@ -3,15 +3,17 @@ use core::iter::Iterator;
 use nom::{
     branch::alt,
     bytes::{complete::tag, take_while, take_while1},
-    combinator::{cut, eof, not, opt, recognize, verify},
+    combinator::{complete, cut, eof, not, opt, recognize, verify},
     multi::separated_list1,
-    sequence::{delimited, preceded, terminated},
+    sequence::{preceded, terminated},
     Parser,
 };
 
 use crate::{
-    atom::{Atom, Category, VersionSuffix},
-    parser_utils::{ignore, search, take_1_if, uncut},
+    atom::{Atom, Category, Cpv, Repo, VersionSuffix},
+    eapi::Eapi,
+    error::Expected,
+    parser_utils::{context, ignore, search, take_1_if, uncut},
     useflag::parsers::usedep,
     ParseResult,
 };
@@ -20,27 +22,31 @@ use super::{
     Blocker, Name, Slot, SlotOperator, Version, VersionNumber, VersionOperator, VersionSuffixKind,
 };
 
-pub fn category(input: &str) -> ParseResult<Category> {
-    recognize((
-        take_1_if(|c: char| c.is_ascii_alphanumeric() || matches!(c, '_')),
-        take_while1(|c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')),
-    ))
+pub fn category(input: &str) -> ParseResult<'_, Category> {
+    context(
+        Expected::Category,
+        recognize((
+            take_1_if(|c: char| c.is_ascii_alphanumeric() || matches!(c, '_')),
+            take_while1(|c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')),
+        )),
+    )
     .map(|input: &str| Category(input.to_string()))
     .parse_complete(input)
 }
 
-pub fn name(input: &str) -> ParseResult<Name> {
-    verify(
+pub fn name(input: &str) -> ParseResult<'_, Name> {
+    let name = verify(
         recognize((
             take_1_if(|c: char| c.is_ascii_alphanumeric() || matches!(c, '_')),
             search(alt((
                 ignore(eof),
+                // the version only ends the name if nothing that could still
+                // be part of the name follows it, e.g. bar-2-baz-1.0
                 ignore((
                     preceded(tag("-"), uncut(version)),
-                    not(search((
-                        preceded(tag("-"), uncut(version)),
-                        alt((eof, search(tag(":")))),
-                    ))),
+                    not(complete(take_1_if(|c: char| {
+                        c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+')
+                    }))),
                 )),
                 ignore(not(take_1_if(|c: char| {
                     c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+')
@@ -52,21 +58,25 @@ pub fn name(input: &str) -> ParseResult<Name> {
                 .parse_complete(result)
                 .is_err()
         },
-    )
-    .map(|result| Name(result.to_string()))
-    .parse_complete(input)
+    );
+
+    context(Expected::Name, name)
+        .map(|result| Name(result.to_string()))
+        .parse_complete(input)
 }
 
-pub fn version(input: &str) -> ParseResult<Version> {
+pub fn version(input: &str) -> ParseResult<'_, Version> {
     let numbers = separated_list1(tag("."), version_number);
     let suffixes = separated_list1(tag("_"), version_suffix);
 
-    (
+    let version = (
         numbers,
         opt(take_1_if(|c: char| c.is_ascii_lowercase()).map(|s: &str| s.chars().next().unwrap())),
         opt(preceded(tag("_"), cut(suffixes))),
         opt(preceded(tag("-"), cut(version_revision))),
-    )
+    );
+
+    context(Expected::Version, version)
         .map(|(numbers, letter, suffixes, revision)| Version {
             numbers,
             letter,
@@ -76,35 +86,117 @@ pub fn version(input: &str) -> ParseResult<Version> {
         .parse_complete(input)
 }
 
-pub fn atom(input: &str) -> ParseResult<Atom> {
-    (
+pub fn atom(input: &str) -> ParseResult<'_, Atom> {
+    let atom = (
         opt(blocker),
         opt(version_operator),
         terminated(category, tag("/")),
         name,
         opt(preceded(tag("-"), cut(version))),
-        opt(preceded(tag(":"), cut(slot))),
-        opt(delimited(
+        opt(tag("*")),
+        opt(preceded((tag(":"), not(tag(":"))), cut(slot))),
+        opt(preceded(tag("::"), cut(repo))),
+        opt(preceded(
             tag("["),
-            cut(separated_list1(tag(","), usedep)),
-            tag("]"),
+            cut(terminated(separated_list1(tag(","), cut(usedep)), tag("]"))),
         )),
-    )
+    );
+
+    // the glob is only legal with the = operator
+    let atom = verify(
+        atom,
+        |(_, version_operator, _, _, version, glob, _, _, _)| {
+            glob.is_none()
+                || (version.is_some() && matches!(version_operator, Some(VersionOperator::Eq)))
+        },
+    );
+
+    context(Expected::Atom, atom)
         .map(
-            |(blocker, version_operator, category, name, version, slot, usedeps)| Atom {
-                blocker,
-                version_operator,
-                category,
-                name,
-                version,
-                slot,
-                usedeps: usedeps.unwrap_or_default(),
+            |(blocker, version_operator, category, name, version, glob, slot, repo, usedeps)| {
+                Atom {
+                    blocker,
+                    version_operator: match glob {
+                        Some(_) => Some(VersionOperator::EqGlob),
+                        None => version_operator,
+                    },
+                    category,
+                    name,
+                    version,
+                    slot,
+                    repo,
+                    usedeps: usedeps.unwrap_or_default(),
+                }
             },
         )
         .parse_complete(input)
 }
 
-fn slot(input: &str) -> ParseResult<Slot> {
+pub fn eapi_atom(eapi: Eapi) -> impl Fn(&str) -> ParseResult<'_, Atom> {
+    move |input| verify(atom, |atom: &Atom| atom_supported(eapi, atom)).parse_complete(input)
+}
+
+// repository names follow the same rules as package names
+pub fn repo(input: &str) -> ParseResult<'_, Repo> {
+    let repo = verify(
+        recognize((
+            take_1_if(|c: char| c.is_ascii_alphanumeric() || matches!(c, '_')),
+            take_while(|c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-')),
+        )),
+        |result: &str| {
+            search((preceded(tag("-"), uncut(version)), eof))
+                .parse_complete(result)
+                .is_err()
+        },
+    );
+
+    context(Expected::Repo, repo)
+        .map(|result: &str| Repo(result.to_string()))
+        .parse_complete(input)
+}
+
+pub fn cpv(input: &str) -> ParseResult<'_, Cpv> {
+    let cpv = (
+        terminated(category, tag("/")),
+        name,
+        preceded(tag("-"), cut(version)),
+    );
+
+    context(Expected::Cpv, cpv)
+        .map(|(category, name, version)| Cpv {
+            category,
+            name,
+            version,
+        })
+        .parse_complete(input)
+}
+
+pub(crate) fn atom_supported(eapi: Eapi, atom: &Atom) -> bool {
+    let blocker = match atom.blocker() {
+        Some(Blocker::Strong) => eapi.has_strong_blockers(),
+        _ => true,
+    };
+
+    let slot = match atom.slot() {
+        Some(slot) => {
+            eapi.has_slot_deps()
+                && (eapi.has_slot_operators()
+                    || (slot.operator().is_none() && !matches!(slot.primary(), None | Some("*"))))
+                && (eapi.has_subslots() || slot.sub().is_none())
+        }
+        None => true,
+    };
+
+    let usedeps = atom.usedeps().len() == 0
+        || (eapi.has_use_deps()
+            && (eapi.has_use_dep_defaults()
+                || atom.usedeps().all(|usedep| usedep.sign().is_none())));
+
+    // repository dependencies are a portage extension and never valid in ebuilds
+    blocker && slot && usedeps && atom.repo().is_none()
+}
+
+pub fn slot(input: &str) -> ParseResult<'_, Slot> {
     let primary = || {
         recognize((
             take_1_if(|c: char| c.is_ascii_alphanumeric() || matches!(c, '_')),
@@ -125,33 +217,42 @@ fn slot(input: &str) -> ParseResult<Slot> {
 
     let slot = (
         primary(),
-        opt(preceded(tag("/"), cut(subslot()))),
+        opt(preceded(
+            tag("/"),
+            cut(context(Expected::Subslot, subslot())),
+        )),
         opt(operator()),
     )
         .map(|(primary, sub, operator)| Slot {
-            primary,
+            primary: Some(primary),
             sub,
             operator,
         });
 
     let wildcard =
         (tag("*"), not((tag("/"), subslot())), opt(operator())).map(|(_, _, operator)| Slot {
-            primary: String::from("*"),
+            primary: Some(String::from("*")),
             sub: None,
             operator,
         });
 
-    alt((slot, wildcard)).parse_complete(input)
+    let operator_only = operator().map(|operator| Slot {
+        primary: None,
+        sub: None,
+        operator: Some(operator),
+    });
+
+    context(Expected::Slot, alt((slot, wildcard, operator_only))).parse_complete(input)
 }
 
-fn blocker(input: &str) -> ParseResult<Blocker> {
+fn blocker(input: &str) -> ParseResult<'_, Blocker> {
     let weak = tag("!").map(|_| Blocker::Weak);
     let strong = tag("!!").map(|_| Blocker::Strong);
 
-    alt((strong, weak)).parse_complete(input)
+    context(Expected::Blocker, alt((strong, weak))).parse_complete(input)
 }
 
-fn version_operator(input: &str) -> ParseResult<VersionOperator> {
+fn version_operator(input: &str) -> ParseResult<'_, VersionOperator> {
     let eq = tag("=").map(|_| VersionOperator::Eq);
     let lt = tag("<").map(|_| VersionOperator::Lt);
     let lt_eq = tag("<=").map(|_| VersionOperator::LtEq);
@@ -159,20 +260,24 @@ fn version_operator(input: &str) -> ParseResult<VersionOperator> {
     let gt_eq = tag(">=").map(|_| VersionOperator::GtEq);
     let roughly = tag("~").map(|_| VersionOperator::Roughly);
 
-    alt((lt_eq, gt_eq, eq, lt, gt, roughly)).parse_complete(input)
+    context(
+        Expected::VersionOperator,
+        alt((lt_eq, gt_eq, eq, lt, gt, roughly)),
+    )
+    .parse_complete(input)
 }
 
-fn version_number(input: &str) -> ParseResult<VersionNumber> {
+fn version_number(input: &str) -> ParseResult<'_, VersionNumber> {
     take_while1(|c: char| c.is_ascii_digit())
         .map(|result: &str| VersionNumber(result.to_string()))
         .parse_complete(input)
 }
 
-fn version_revision(input: &str) -> ParseResult<VersionNumber> {
-    preceded(tag("r"), version_number).parse_complete(input)
+fn version_revision(input: &str) -> ParseResult<'_, VersionNumber> {
+    context(Expected::Revision, preceded(tag("r"), version_number)).parse_complete(input)
 }
 
-fn version_suffix(input: &str) -> ParseResult<VersionSuffix> {
+fn version_suffix(input: &str) -> ParseResult<'_, VersionSuffix> {
     let alpha = tag("alpha").map(|_| VersionSuffixKind::Alpha);
     let beta = tag("beta").map(|_| VersionSuffixKind::Beta);
     let pre = tag("pre").map(|_| VersionSuffixKind::Pre);
@@ -181,7 +286,7 @@ fn version_suffix(input: &str) -> ParseResult<VersionSuffix> {
 
     let suffix = alt((alpha, beta, pre, rc, p));
 
-    (suffix, opt(version_number))
+    context(Expected::VersionSuffix, (suffix, opt(version_number)))
         .map(|(kind, number)| VersionSuffix { kind, number })
         .parse_complete(input)
 }
@@ -333,10 +438,176 @@ mod tests {
         assert_eq!(atom.name().get(), "bar-2-baz");
     }
 
+    #[test]
+    fn test_atom_followed_by_other_input() {
+        let input = ">=cat/foo-1.0[bar] >=cat/baz-2.0:1";
+
+        let (rest, atom) = atom(input).unwrap();
+
+        assert_eq!(atom.name().get(), "foo");
+
+        assert_eq!(rest, " >=cat/baz-2.0:1");
+    }
+
     #[test]
     fn test_atom_with_star_in_non_empty_slot() {
         let input = "foo/bar-1.0.0:*/subslot";
 
         assert!(atom(input).is_err());
     }
+
+    #[test]
+    fn test_cpv() {
+        let input = "sys-apps/portage-3.0.66-r1";
+
+        let (_, cpv) = terminated(cpv, eof).parse_complete(input).unwrap();
+
+        assert_eq!(cpv.category().get(), "sys-apps");
+
+        assert_eq!(cpv.name().get(), "portage");
+
+        assert_eq!(cpv.version().revision().unwrap().get(), "1");
+
+        assert_eq!(cpv.to_string(), input);
+
+        assert_eq!(Atom::from(cpv).to_string(), format!("={input}"));
+    }
+
+    #[test]
+    fn test_cpv_with_version_in_name() {
+        let input = "dev-libs/foo-2-bar-1.0";
+
+        let (_, cpv) = terminated(cpv, eof).parse_complete(input).unwrap();
+
+        assert_eq!(cpv.name().get(), "foo-2-bar");
+    }
+
+    #[test]
+    fn test_cpv_without_version() {
+        let input = "sys-apps/portage";
+
+        assert!(cpv(input).is_err());
+    }
+
+    #[test]
+    fn test_cpv_ordering() {
+        let mut cpvs = [
+            "sys-apps/portage-3.0.66-r1",
+            "dev-lang/rust-1.80.1",
+            "sys-apps/portage-3.0.9",
+            "dev-lang/python-3.12.7",
+            "dev-lang/rust-1.79.0",
+        ]
+        .map(|input| cpv(input).unwrap().1);
+
+        cpvs.sort();
+
+        assert_eq!(
+            cpvs.map(|cpv| cpv.to_string()),
+            [
+                "dev-lang/python-3.12.7",
+                "dev-lang/rust-1.79.0",
+                "dev-lang/rust-1.80.1",
+                "sys-apps/portage-3.0.9",
+                "sys-apps/portage-3.0.66-r1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_atom_with_glob() {
+        let input = "=dev-lang/python-3.12*:3.12[sqlite]";
+
+        let (_, atom) = terminated(atom, eof).parse_complete(input).unwrap();
+
+        assert_eq!(atom.version_operator(), Some(VersionOperator::EqGlob));
+
+        assert_eq!(atom.name().get(), "python");
+
+        assert_eq!(atom.to_string(), input);
+    }
+
+    #[test]
+    fn test_atom_with_glob_and_invalid_operator() {
+        for input in [
+            ">=dev-lang/python-3.12*",
+            "~dev-lang/python-3.12*",
+            "dev-lang/python-3.12*",
+            "=dev-lang/python*",
+        ] {
+            assert!(
+                terminated(atom, eof).parse_complete(input).is_err(),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_atom_with_repo() {
+        for input in [
+            "dev-lang/rust::gentoo",
+            "dev-lang/rust:stable::gentoo",
+            ">=dev-lang/rust-1.80:stable/1.80::guru[llvm]",
+            "=dev-lang/rust-1.80*::my_overlay-ext",
+        ] {
+            let (_, atom) = terminated(atom, eof).parse_complete(input).unwrap();
+
+            assert_eq!(atom.to_string(), input);
+        }
+
+        let (_, atom) = atom("dev-lang/rust:stable::gentoo").unwrap();
+
+        assert_eq!(atom.slot().unwrap().primary(), Some("stable"));
+
+        assert_eq!(atom.repo().unwrap().get(), "gentoo");
+    }
+
+    #[test]
+    fn test_atom_with_invalid_repo() {
+        for input in [
+            "dev-lang/rust::",
+            "dev-lang/rust::-gentoo",
+            "dev-lang/rust::gentoo-1.0",
+            "dev-lang/rust::gen.too",
+        ] {
+            assert!(
+                terminated(atom, eof).parse_complete(input).is_err(),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_eapi_atom() {
+        let table = [
+            ("cat/pkg", Some(Eapi::Zero)),
+            (">=cat/pkg-1.0", Some(Eapi::Zero)),
+            ("=cat/pkg-1*", Some(Eapi::Zero)),
+            ("!cat/pkg", Some(Eapi::Zero)),
+            ("cat/pkg:2", Some(Eapi::One)),
+            ("!!cat/pkg", Some(Eapi::Two)),
+            ("cat/pkg[foo,-bar,baz?,!qux=]", Some(Eapi::Two)),
+            ("cat/pkg[foo(+)]", Some(Eapi::Four)),
+            ("cat/pkg[-bar(-)]", Some(Eapi::Four)),
+            ("cat/pkg:=", Some(Eapi::Five)),
+            ("cat/pkg:*", Some(Eapi::Five)),
+            ("cat/pkg:2=", Some(Eapi::Five)),
+            ("cat/pkg:2/2.1", Some(Eapi::Five)),
+            ("cat/pkg::gentoo", None),
+        ];
+
+        for (input, since) in table {
+            for eapi in Eapi::ALL {
+                let expected = since.is_some_and(|since| eapi >= since);
+
+                assert_eq!(
+                    terminated(eapi_atom(eapi), eof)
+                        .parse_complete(input)
+                        .is_ok(),
+                    expected,
+                    "{input} in EAPI {eapi}"
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,350 @@
+use core::slice;
+
+use std::collections::HashSet;
+
+use crate::{
+    atom::{Atom, Package},
+    useflag::UseFlag,
+};
+
+use super::{normalize::negate, Conditional, DepSpec, Expr};
+
+// an installed package caught by a blocker
+#[derive(Clone, Debug)]
+pub struct BlockerMatch<'a> {
+    blocker: &'a Atom,
+    conditionals: Vec<&'a Conditional>,
+    package: &'a Package,
+}
+
+impl<'a> BlockerMatch<'a> {
+    pub fn blocker(&self) -> &'a Atom {
+        self.blocker
+    }
+
+    pub fn conditionals(&self) -> &[&'a Conditional] {
+        &self.conditionals
+    }
+
+    pub fn package(&self) -> &'a Package {
+        self.package
+    }
+}
+
+impl DepSpec {
+    // every blocker along with the conditionals guarding it
+    pub fn blockers(&self) -> impl Iterator<Item = (&Atom, Vec<&Conditional>)> {
+        self.guarded_atoms()
+            .filter(|(atom, _)| atom.blocker().is_some())
+    }
+
+    // the specification with every blocker removed, groups that are left
+    // empty are removed along with them. a group of alternatives where one
+    // of the alternatives only held blockers is removed as a whole, as that
+    // alternative is met by not installing anything
+    pub fn without_blockers(&self) -> DepSpec {
+        DepSpec(without_blockers(&self.0))
+    }
+
+    // checks the blockers that apply to `package`, the package that owns the
+    // dependencies, against the installed packages. conditionals and usedeps
+    // on the blockers are resolved against its USE flags, blockers matching
+    // the package itself are ignored, and so are blockers that are only one
+    // alternative of a group, as whether they apply depends on the choice the
+    // package manager makes for the group
+    pub fn blocked<'a, I>(&'a self, package: &Package, installed: I) -> Vec<BlockerMatch<'a>>
+    where
+        I: IntoIterator<Item = &'a Package>,
+    {
+        let mut blockers = Vec::new();
+
+        applicable_blockers(&self.0, &package.useflags, &mut Vec::new(), &mut blockers);
+
+        blockers.retain(|(blocker, _)| !blocker.matches_with_parent(package, &package.useflags));
+
+        let mut matches = Vec::new();
+
+        for installed in installed {
+            for (blocker, conditionals) in &blockers {
+                if blocker.matches_with_parent(installed, &package.useflags) {
+                    matches.push(BlockerMatch {
+                        blocker,
+                        conditionals: conditionals.clone(),
+                        package: installed,
+                    });
+                }
+            }
+        }
+
+        matches
+    }
+}
+
+// descends into all-of groups and the conditionals that are enabled, but not
+// into groups of alternatives
+fn applicable_blockers<'a>(
+    exprs: &'a [Expr],
+    useflags: &HashSet<UseFlag>,
+    conditionals: &mut Vec<&'a Conditional>,
+    out: &mut Vec<(&'a Atom, Vec<&'a Conditional>)>,
+) {
+    for expr in exprs {
+        match expr {
+            Expr::Atom(atom) if atom.blocker().is_some() => out.push((atom, conditionals.clone())),
+            Expr::AllOf(exprs) => applicable_blockers(exprs, useflags, conditionals, out),
+            Expr::Condtional(conditional, exprs) => {
+                if conditional.enabled(useflags) {
+                    conditionals.push(conditional);
+                    applicable_blockers(exprs, useflags, conditionals, out);
+                    conditionals.pop();
+                }
+            }
+            Expr::Atom(_)
+            | Expr::UseRequirement(_)
+            | Expr::AnyOf(_)
+            | Expr::OneOf(_)
+            | Expr::AtMostOneOf(_) => (),
+        }
+    }
+}
+
+fn without_blockers(exprs: &[Expr]) -> Vec<Expr> {
+    exprs
+        .iter()
+        .filter_map(|expr| match expr {
+            Expr::Atom(atom) if atom.blocker().is_some() => None,
+            Expr::Atom(_) | Expr::UseRequirement(_) => Some(expr.clone()),
+            Expr::AllOf(exprs) => group(exprs, Expr::AllOf),
+            Expr::AnyOf(exprs) => alternatives(exprs, Expr::AnyOf),
+            Expr::OneOf(exprs) => alternatives(exprs, Expr::OneOf),
+            Expr::AtMostOneOf(exprs) => alternatives(exprs, Expr::AtMostOneOf),
+            Expr::Condtional(conditional, exprs) => {
+                group(exprs, |exprs| Expr::Condtional(conditional.clone(), exprs))
+            }
+        })
+        .collect()
+}
+
+fn group(exprs: &[Expr], f: impl FnOnce(Vec<Expr>) -> Expr) -> Option<Expr> {
+    let exprs = without_blockers(exprs);
+
+    if exprs.is_empty() {
+        None
+    } else {
+        Some(f(exprs))
+    }
+}
+
+// an alternative made up only of blockers satisfies the group on its own, so
+// the group is dropped when such an alternative is always there. when it is
+// behind a conditional the rest of the group still has to be satisfied while
+// the conditional is disabled
+fn alternatives(exprs: &[Expr], f: impl FnOnce(Vec<Expr>) -> Expr) -> Option<Expr> {
+    let mut alternatives = Vec::new();
+    let mut guards = Vec::new();
+
+    for expr in exprs {
+        match (without_blockers(slice::from_ref(expr)).pop(), expr) {
+            (Some(alternative), _) => alternatives.push(alternative),
+            (None, Expr::Condtional(conditional, _)) => guards.push(negate(conditional)),
+            (None, _) => return None,
+        }
+    }
+
+    if alternatives.is_empty() {
+        return None;
+    }
+
+    let group = match f(alternatives) {
+        Expr::AnyOf(mut alternatives) | Expr::OneOf(mut alternatives)
+            if !guards.is_empty() && alternatives.len() == 1 =>
+        {
+            alternatives.pop().unwrap()
+        }
+        group => group,
+    };
+
+    Some(
+        guards
+            .into_iter()
+            .rev()
+            .fold(group, |expr, guard| Expr::Condtional(guard, vec![expr])),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{atom::Cpv, test_utils::useflags};
+
+    use super::*;
+
+    fn package(cpv: &str, flags: &[&str]) -> Package {
+        let cpv = cpv.parse::<Cpv>().unwrap();
+
+        Package {
+            category: cpv.category().clone(),
+            name: cpv.name().clone(),
+            version: cpv.version().clone(),
+            slot: "0".to_string(),
+            subslot: None,
+            repo: None,
+            iuse: useflags(flags),
+            useflags: useflags(flags),
+        }
+    }
+
+    #[test]
+    fn test_blockers() {
+        let spec = "cat/a !cat/b foo? ( !!<cat/c-2 cat/d ) || ( !cat/e cat/f )"
+            .parse::<DepSpec>()
+            .unwrap();
+
+        let blockers = spec
+            .blockers()
+            .map(|(atom, conditionals)| (atom.to_string(), conditionals.len()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            blockers,
+            [
+                ("!cat/b".to_string(), 0),
+                ("!!<cat/c-2".to_string(), 1),
+                ("!cat/e".to_string(), 0)
+            ]
+        );
+
+        assert_eq!(spec.without_blockers().to_string(), "cat/a foo? ( cat/d )");
+
+        let spec = "foo? ( !cat/b )".parse::<DepSpec>().unwrap();
+
+        assert!(spec.without_blockers().is_empty());
+    }
+
+    #[test]
+    fn test_without_blockers_in_alternatives() {
+        let table = [
+            ("|| ( !cat/e cat/f )", ""),
+            ("|| ( !cat/e )", ""),
+            (
+                "|| ( foo? ( !cat/e ) cat/f ) cat/g",
+                "!foo? ( cat/f ) cat/g",
+            ),
+            (
+                "|| ( foo? ( !cat/e ) !bar? ( !cat/f ) cat/g cat/h )",
+                "!foo? ( bar? ( || ( cat/g cat/h ) ) )",
+            ),
+            ("|| ( foo? ( !cat/e ) !cat/f cat/g )", ""),
+            ("|| ( foo? ( !cat/e ) )", ""),
+            (
+                "^^ ( foo? ( !cat/e ) cat/f cat/g )",
+                "!foo? ( ^^ ( cat/f cat/g ) )",
+            ),
+            (
+                "|| ( foo? ( !cat/e cat/g ) cat/f )",
+                "|| ( foo? ( cat/g ) cat/f )",
+            ),
+            ("|| ( ( !cat/e cat/g ) cat/f )", "|| ( ( cat/g ) cat/f )"),
+            (
+                "foo? ( || ( cat/a cat/b ) !cat/c )",
+                "foo? ( || ( cat/a cat/b ) )",
+            ),
+        ];
+
+        for (input, expected) in table {
+            let spec = input.parse::<DepSpec>().unwrap();
+
+            assert_eq!(spec.without_blockers().to_string(), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_blocked() {
+        let spec = "!cat/a foo? ( !!<cat/b-2 ) !cat/c[bar]"
+            .parse::<DepSpec>()
+            .unwrap();
+
+        let installed = [
+            package("cat/a-1", &[]),
+            package("cat/b-1", &[]),
+            package("cat/b-3", &[]),
+            package("cat/c-1", &[]),
+            package("cat/d-1", &[]),
+        ];
+
+        let table = [
+            (&[][..], &[("!cat/a", "cat/a-1")][..]),
+            (
+                &["foo"][..],
+                &[("!cat/a", "cat/a-1"), ("!!<cat/b-2", "cat/b-1")][..],
+            ),
+        ];
+
+        for (flags, expected) in table {
+            let blocked = spec
+                .blocked(&package("cat/owner-1", flags), &installed)
+                .into_iter()
+                .map(|blocked| {
+                    let package = blocked.package();
+
+                    (
+                        blocked.blocker().to_string(),
+                        format!("{}/{}-{}", package.category, package.name, package.version),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let expected = expected
+                .iter()
+                .map(|(blocker, package)| (blocker.to_string(), package.to_string()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(blocked, expected, "{flags:?}");
+        }
+
+        let installed = [package("cat/c-1", &["bar"])];
+
+        assert_eq!(
+            spec.blocked(&package("cat/owner-1", &[]), &installed).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_blocked_alternatives() {
+        let spec = "|| ( !cat/a cat/b ) foo? ( ^^ ( !cat/c cat/d ) )"
+            .parse::<DepSpec>()
+            .unwrap();
+
+        let installed = [package("cat/a-1", &[]), package("cat/c-1", &[])];
+
+        assert!(spec
+            .blocked(&package("cat/owner-1", &["foo"]), &installed)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_blocked_by_itself() {
+        // a package blocking older versions of itself
+        let spec = "!<cat/pkg-2 !cat/other".parse::<DepSpec>().unwrap();
+
+        let installed = [package("cat/pkg-1", &[]), package("cat/other-1", &[])];
+
+        let blocked = spec
+            .blocked(&package("cat/pkg-2", &[]), &installed)
+            .into_iter()
+            .map(|blocked| blocked.blocker().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(blocked, ["!<cat/pkg-2", "!cat/other"]);
+
+        // but not the package itself
+        let spec = "!cat/pkg".parse::<DepSpec>().unwrap();
+
+        let installed = [package("cat/pkg-1", &[]), package("cat/pkg-2", &[])];
+
+        assert!(spec
+            .blocked(&package("cat/pkg-2", &[]), &installed)
+            .is_empty());
+    }
+}
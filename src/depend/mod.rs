@@ -1,25 +1,649 @@
+mod blockers;
+mod normalize;
 pub mod parsers;
+mod visit;
 
-use crate::{atom::Atom, useflag::UseFlag};
+use core::{
+    fmt::{self, Display},
+    iter::ExactSizeIterator,
+    slice,
+    str::FromStr,
+    write,
+};
+use std::{collections::HashSet, fs, io, path::Path};
 
-#[derive(Clone, Debug)]
+pub use blockers::BlockerMatch;
+pub use visit::{Visitor, VisitorMut};
+
+use crate::{atom::Atom, eapi::Eapi, error::Error, parser_utils::parse_all, useflag::UseFlag, vdb};
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Conditional {
     Negative(UseFlag),
     Positive(UseFlag),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UseRequirement {
     Negative(UseFlag),
     Positive(UseFlag),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expr {
     Atom(Atom),
     UseRequirement(UseRequirement),
     AllOf(Vec<Expr>),
     AnyOf(Vec<Expr>),
     OneOf(Vec<Expr>),
+    AtMostOneOf(Vec<Expr>),
     Condtional(Conditional, Vec<Expr>),
 }
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DepSpec(Vec<Expr>);
+
+// the dependency variables an ebuild can set
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Class {
+    Depend,
+    Rdepend,
+    Bdepend,
+    Pdepend,
+    Idepend,
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dependencies {
+    depend: DepSpec,
+    rdepend: DepSpec,
+    bdepend: DepSpec,
+    pdepend: DepSpec,
+    idepend: DepSpec,
+}
+
+// every atom in a dependency specification, regardless of the conditionals
+// and groups around it
+pub struct Atoms<'a> {
+    stack: Vec<slice::Iter<'a, Expr>>,
+}
+
+impl DepSpec {
+    pub fn exprs(&self) -> impl ExactSizeIterator<Item = &Expr> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn atoms(&self) -> Atoms<'_> {
+        Atoms {
+            stack: vec![self.0.iter()],
+        }
+    }
+}
+
+impl Class {
+    pub const ALL: [Class; 5] = [
+        Class::Depend,
+        Class::Rdepend,
+        Class::Bdepend,
+        Class::Pdepend,
+        Class::Idepend,
+    ];
+
+    // the name of the variable, which is also the name of the md5-cache key
+    // and the VDB file the dependencies are stored in
+    pub fn var(self) -> &'static str {
+        match self {
+            Self::Depend => "DEPEND",
+            Self::Rdepend => "RDEPEND",
+            Self::Bdepend => "BDEPEND",
+            Self::Pdepend => "PDEPEND",
+            Self::Idepend => "IDEPEND",
+        }
+    }
+
+    pub fn supported(self, eapi: Eapi) -> bool {
+        match self {
+            Self::Bdepend => eapi.has_bdepend(),
+            Self::Idepend => eapi.has_idepend(),
+            _ => true,
+        }
+    }
+
+    pub fn is_build_time(self) -> bool {
+        matches!(self, Self::Depend | Self::Bdepend)
+    }
+
+    pub fn is_runtime(self) -> bool {
+        matches!(self, Self::Rdepend | Self::Pdepend)
+    }
+}
+
+impl Dependencies {
+    pub fn get(&self, class: Class) -> &DepSpec {
+        match class {
+            Class::Depend => &self.depend,
+            Class::Rdepend => &self.rdepend,
+            Class::Bdepend => &self.bdepend,
+            Class::Pdepend => &self.pdepend,
+            Class::Idepend => &self.idepend,
+        }
+    }
+
+    pub fn set(&mut self, class: Class, spec: DepSpec) {
+        match class {
+            Class::Depend => self.depend = spec,
+            Class::Rdepend => self.rdepend = spec,
+            Class::Bdepend => self.bdepend = spec,
+            Class::Pdepend => self.pdepend = spec,
+            Class::Idepend => self.idepend = spec,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Class, &DepSpec)> {
+        Class::ALL.into_iter().map(|class| (class, self.get(class)))
+    }
+
+    pub fn atoms(&self, class: Class) -> Atoms<'_> {
+        self.get(class).atoms()
+    }
+
+    // DEPEND and BDEPEND
+    pub fn build_time_atoms(&self) -> impl Iterator<Item = &Atom> {
+        self.iter()
+            .filter(|(class, _)| class.is_build_time())
+            .flat_map(|(_, spec)| spec.atoms())
+    }
+
+    // RDEPEND and PDEPEND
+    pub fn runtime_atoms(&self) -> impl Iterator<Item = &Atom> {
+        self.iter()
+            .filter(|(class, _)| class.is_runtime())
+            .flat_map(|(_, spec)| spec.atoms())
+    }
+
+    pub fn evaluate(&self, useflags: &HashSet<UseFlag>) -> Dependencies {
+        let mut dependencies = Dependencies::default();
+
+        for (class, spec) in self.iter() {
+            dependencies.set(class, spec.evaluate(useflags));
+        }
+
+        dependencies
+    }
+
+    // reads the KEY=value lines of an md5-cache entry, each class is parsed
+    // with the rules of the EAPI recorded in the entry and classes the EAPI
+    // does not support are ignored, like the package manager would
+    pub fn from_md5_cache(input: &str) -> Result<Self, Error> {
+        let entries = input.lines().filter_map(|line| line.split_once('='));
+
+        let eapi = match entries.clone().find(|(key, _)| *key == "EAPI") {
+            Some((_, eapi)) => eapi.parse()?,
+            None => Eapi::Zero,
+        };
+
+        let mut dependencies = Dependencies::default();
+
+        for (key, value) in entries {
+            if let Some(class) = Class::ALL
+                .into_iter()
+                .find(|class| class.var() == key && class.supported(eapi))
+            {
+                dependencies.set(class, parse_all(value, parsers::eapi_dep_spec(eapi))?);
+            }
+        }
+
+        Ok(dependencies)
+    }
+
+    // reads the dependency files of an installed package, such as
+    // /var/db/pkg/dev-lang/rust-1.80.1. the directory has to exist but
+    // missing files are left empty, as are classes the EAPI does not support
+    pub fn from_vdb(path: impl AsRef<Path>) -> Result<Self, vdb::Error> {
+        let path = path.as_ref();
+
+        if !fs::metadata(path)?.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                format!("{} is not a directory", path.display()),
+            )
+            .into());
+        }
+
+        let eapi = match read_optional(&path.join("EAPI"))? {
+            Some(eapi) => eapi.trim().parse()?,
+            None => Eapi::Zero,
+        };
+
+        let mut dependencies = Dependencies::default();
+
+        for class in Class::ALL.into_iter().filter(|class| class.supported(eapi)) {
+            if let Some(value) = read_optional(&path.join(class.var()))? {
+                dependencies.set(class, parse_all(&value, parsers::eapi_dep_spec(eapi))?);
+            }
+        }
+
+        Ok(dependencies)
+    }
+}
+
+impl<'a> Iterator for Atoms<'a> {
+    type Item = &'a Atom;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(Expr::Atom(atom)) => return Some(atom),
+                Some(Expr::UseRequirement(_)) => (),
+                Some(
+                    Expr::AllOf(exprs)
+                    | Expr::AnyOf(exprs)
+                    | Expr::OneOf(exprs)
+                    | Expr::AtMostOneOf(exprs)
+                    | Expr::Condtional(_, exprs),
+                ) => self.stack.push(exprs.iter()),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+impl Conditional {
+    pub fn useflag(&self) -> &UseFlag {
+        match self {
+            Self::Negative(flag) | Self::Positive(flag) => flag,
+        }
+    }
+
+    pub fn enabled(&self, useflags: &HashSet<UseFlag>) -> bool {
+        match self {
+            Self::Negative(flag) => !useflags.contains(flag),
+            Self::Positive(flag) => useflags.contains(flag),
+        }
+    }
+}
+
+impl Expr {
+    // resolves every conditional against the enabled USE flags and flattens
+    // all-of groups into their parent, any other groups are kept but dropped
+    // entirely if nothing inside of them is left
+    pub fn evaluate(&self, useflags: &HashSet<UseFlag>) -> Vec<Expr> {
+        let mut exprs = Vec::new();
+
+        evaluate(core::slice::from_ref(self), useflags, &mut exprs);
+
+        exprs
+    }
+}
+
+impl DepSpec {
+    pub fn evaluate(&self, useflags: &HashSet<UseFlag>) -> DepSpec {
+        let mut exprs = Vec::new();
+
+        evaluate(&self.0, useflags, &mut exprs);
+
+        DepSpec(exprs)
+    }
+}
+
+impl From<Vec<Expr>> for DepSpec {
+    fn from(exprs: Vec<Expr>) -> Self {
+        Self(exprs)
+    }
+}
+
+impl From<DepSpec> for Vec<Expr> {
+    fn from(spec: DepSpec) -> Self {
+        spec.0
+    }
+}
+
+impl FromStr for Expr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::expr)
+    }
+}
+
+impl FromStr for DepSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::dep_spec)
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.var())
+    }
+}
+
+impl Display for Conditional {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Negative(flag) => write!(f, "!{}?", flag),
+            Self::Positive(flag) => write!(f, "{}?", flag),
+        }
+    }
+}
+
+impl Display for UseRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Negative(flag) => write!(f, "!{}", flag),
+            Self::Positive(flag) => write!(f, "{}", flag),
+        }
+    }
+}
+
+// the alternate flag (`{:#}`) pretty prints the expression, with one item per
+// line and nested groups indented by tabs
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let depth = if f.alternate() { Some(0) } else { None };
+
+        write_expr(self, depth, f)
+    }
+}
+
+impl Display for DepSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (depth, separator) = if f.alternate() {
+            (Some(0), "\n")
+        } else {
+            (None, " ")
+        };
+
+        for (i, expr) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", separator)?;
+            }
+
+            write_expr(expr, depth, f)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn read_optional(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+fn evaluate(exprs: &[Expr], useflags: &HashSet<UseFlag>, out: &mut Vec<Expr>) {
+    for expr in exprs {
+        match expr {
+            Expr::Atom(_) | Expr::UseRequirement(_) => out.push(expr.clone()),
+            Expr::AllOf(exprs) => evaluate(exprs, useflags, out),
+            Expr::Condtional(conditional, exprs) => {
+                if conditional.enabled(useflags) {
+                    evaluate(exprs, useflags, out);
+                }
+            }
+            Expr::AnyOf(exprs) => {
+                let group = evaluate_group(exprs, useflags);
+
+                if !group.is_empty() {
+                    out.push(Expr::AnyOf(group));
+                }
+            }
+            Expr::OneOf(exprs) => {
+                let group = evaluate_group(exprs, useflags);
+
+                if !group.is_empty() {
+                    out.push(Expr::OneOf(group));
+                }
+            }
+            Expr::AtMostOneOf(exprs) => {
+                let group = evaluate_group(exprs, useflags);
+
+                if !group.is_empty() {
+                    out.push(Expr::AtMostOneOf(group));
+                }
+            }
+        }
+    }
+}
+
+// each member of a group has to stay a single expression, so anything that
+// evaluates to more than one is wrapped back up in an all-of group
+fn evaluate_group(exprs: &[Expr], useflags: &HashSet<UseFlag>) -> Vec<Expr> {
+    let mut group = Vec::new();
+
+    for expr in exprs {
+        let mut members = expr.evaluate(useflags);
+
+        match members.len() {
+            0 => (),
+            1 => group.push(members.remove(0)),
+            _ => group.push(Expr::AllOf(members)),
+        }
+    }
+
+    group
+}
+
+fn write_expr(expr: &Expr, depth: Option<usize>, f: &mut fmt::Formatter) -> fmt::Result {
+    match expr {
+        Expr::Atom(atom) => write!(f, "{}", atom),
+        Expr::UseRequirement(requirement) => write!(f, "{}", requirement),
+        Expr::AllOf(exprs) => write_group(exprs, depth, f, write_expr),
+        Expr::AnyOf(exprs) => {
+            write!(f, "|| ")?;
+            write_group(exprs, depth, f, write_expr)
+        }
+        Expr::OneOf(exprs) => {
+            write!(f, "^^ ")?;
+            write_group(exprs, depth, f, write_expr)
+        }
+        Expr::AtMostOneOf(exprs) => {
+            write!(f, "?? ")?;
+            write_group(exprs, depth, f, write_expr)
+        }
+        Expr::Condtional(conditional, exprs) => {
+            write!(f, "{} ", conditional)?;
+            write_group(exprs, depth, f, write_expr)
+        }
+    }
+}
+
+// writes a parenthesized group the way every specification that follows the
+// dependency grammar spells it. without a depth the group is written on one
+// line, with one the items go on their own lines, indented by tabs. `item`
+// writes a single member at the depth it is given
+pub(crate) fn write_group<T, F>(
+    items: &[T],
+    depth: Option<usize>,
+    f: &mut fmt::Formatter,
+    mut item: F,
+) -> fmt::Result
+where
+    F: FnMut(&T, Option<usize>, &mut fmt::Formatter) -> fmt::Result,
+{
+    write!(f, "(")?;
+
+    match depth {
+        Some(depth) => {
+            for member in items {
+                write!(f, "\n{}", "\t".repeat(depth + 1))?;
+                item(member, Some(depth + 1), f)?;
+            }
+
+            write!(f, "\n{})", "\t".repeat(depth))
+        }
+        None => {
+            for member in items {
+                write!(f, " ")?;
+                item(member, None, f)?;
+            }
+
+            write!(f, " )")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test_utils::useflags;
+
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let input = "cat/a foo? ( cat/b !bar? ( cat/c ) ) !foo? ( cat/d ) ( cat/e ( cat/f ) ) || ( baz? ( cat/g cat/h ) qux? ( cat/i ) cat/j ) || ( qux? ( cat/k ) )";
+
+        let spec = input.parse::<DepSpec>().unwrap();
+
+        let table = [
+            (&[][..], "cat/a cat/d cat/e cat/f || ( cat/j )"),
+            (
+                &["foo", "baz"][..],
+                "cat/a cat/b cat/c cat/e cat/f || ( ( cat/g cat/h ) cat/j )",
+            ),
+            (
+                &["foo", "bar", "qux"][..],
+                "cat/a cat/b cat/e cat/f || ( cat/i cat/j ) || ( cat/k )",
+            ),
+        ];
+
+        for (flags, expected) in table {
+            assert_eq!(
+                spec.evaluate(&useflags(flags)).to_string(),
+                expected,
+                "{flags:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_atoms() {
+        let spec = "cat/a foo? ( cat/b || ( cat/c !bar? ( cat/d ) ) ) ( cat/e )"
+            .parse::<DepSpec>()
+            .unwrap();
+
+        let atoms = spec
+            .atoms()
+            .map(|atom| atom.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(atoms, ["cat/a", "cat/b", "cat/c", "cat/d", "cat/e"]);
+    }
+
+    #[test]
+    fn test_from_md5_cache() {
+        let input = "BDEPEND=virtual/pkgconfig test? ( dev-util/cmocka )\nDEFINED_PHASES=compile configure install test\nDEPEND=sys-libs/zlib:= ssl? ( dev-libs/openssl:0= )\nEAPI=8\nIUSE=ssl test\nRDEPEND=sys-libs/zlib:= ssl? ( dev-libs/openssl:0= )\nSLOT=0\n";
+
+        let dependencies = Dependencies::from_md5_cache(input).unwrap();
+
+        let build_time = dependencies
+            .build_time_atoms()
+            .map(|atom| atom.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            build_time,
+            [
+                "sys-libs/zlib:=",
+                "dev-libs/openssl:0=",
+                "virtual/pkgconfig",
+                "dev-util/cmocka"
+            ]
+        );
+
+        assert_eq!(dependencies.runtime_atoms().count(), 2);
+
+        assert!(dependencies.get(Class::Pdepend).is_empty());
+
+        assert_eq!(
+            dependencies
+                .evaluate(&useflags(&[]))
+                .get(Class::Rdepend)
+                .to_string(),
+            "sys-libs/zlib:="
+        );
+
+        // slot operators are not allowed before EAPI 5
+        assert!(Dependencies::from_md5_cache("DEPEND=sys-libs/zlib:=\nEAPI=4\n").is_err());
+
+        // IDEPEND only exists since EAPI 8 and BDEPEND since EAPI 7
+        let dependencies =
+            Dependencies::from_md5_cache("BDEPEND=cat/a\nEAPI=7\nIDEPEND=cat/b\nRDEPEND=cat/c\n")
+                .unwrap();
+
+        assert_eq!(dependencies.get(Class::Bdepend).to_string(), "cat/a");
+
+        assert!(dependencies.get(Class::Idepend).is_empty());
+
+        assert!(Dependencies::from_md5_cache("BDEPEND=cat/a\nEAPI=6\n")
+            .unwrap()
+            .get(Class::Bdepend)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_from_vdb() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testdata/vdb/dev-lang/python-3.12.7"
+        );
+
+        let dependencies = Dependencies::from_vdb(path).unwrap();
+
+        assert!(dependencies
+            .atoms(Class::Rdepend)
+            .any(|atom| atom.to_string() == "dev-libs/libffi:="));
+
+        assert!(dependencies.get(Class::Idepend).is_empty());
+
+        assert!(matches!(
+            Dependencies::from_vdb("/nonexistent"),
+            Err(vdb::Error::Io(error)) if error.kind() == io::ErrorKind::NotFound
+        ));
+
+        assert!(matches!(
+            Dependencies::from_vdb(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")),
+            Err(vdb::Error::Io(error)) if error.kind() == io::ErrorKind::NotADirectory
+        ));
+
+        // app-misc/hello only has a SLOT and CONTENTS
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testdata/vdb/app-misc/hello-2.12.1-r1"
+        );
+
+        assert!(Dependencies::from_vdb(path)
+            .unwrap()
+            .iter()
+            .all(|(_, spec)| spec.is_empty()));
+    }
+
+    #[test]
+    fn test_evaluate_expr() {
+        let spec = "foo? ( cat/a cat/b )".parse::<DepSpec>().unwrap();
+
+        let expr = spec.exprs().next().unwrap();
+
+        assert_eq!(expr.evaluate(&useflags(&["foo"])).len(), 2);
+
+        assert!(expr.evaluate(&useflags(&[])).is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+use core::cmp::Ordering;
+
+use super::{Conditional, DepSpec, Expr};
+
+impl DepSpec {
+    // rewrites the specification into a canonical form, so that
+    // specifications that mean the same thing compare equal:
+    //
+    // - all-of groups are flattened into their parent
+    // - conditionals on the same flag are merged, and conditionals already
+    //   implied (or contradicted) by an outer conditional are removed
+    // - any-of and exactly-one-of groups with a single member are replaced by
+    //   the member, at-most-one-of groups with a single member are dropped
+    // - duplicates are removed and everything except any-of groups, whose
+    //   order expresses a preference, is sorted
+    pub fn normalize(&self) -> DepSpec {
+        let mut exprs = self.0.clone();
+
+        // each pass can expose more work for the next one, such as an any-of
+        // group collapsing into a conditional that can be merged
+        loop {
+            let next = normalize_list(&exprs, &mut Vec::new());
+
+            if next == exprs {
+                return DepSpec(exprs);
+            }
+
+            exprs = next;
+        }
+    }
+}
+
+fn normalize_list(exprs: &[Expr], guards: &mut Vec<Conditional>) -> Vec<Expr> {
+    let mut flat = Vec::new();
+
+    flatten(exprs, guards, &mut flat);
+
+    let mut out = Vec::new();
+
+    for expr in flat {
+        out.extend(normalize_expr(expr, guards));
+    }
+
+    out.sort_by(compare);
+    out.dedup();
+
+    out
+}
+
+// splices all-of groups and conditionals implied by the guards into the
+// list, and merges conditionals on the same flag
+fn flatten(exprs: &[Expr], guards: &[Conditional], out: &mut Vec<Expr>) {
+    for expr in exprs {
+        match expr {
+            Expr::AllOf(exprs) => flatten(exprs, guards, out),
+            Expr::Condtional(conditional, exprs) if guards.contains(conditional) => {
+                flatten(exprs, guards, out)
+            }
+            Expr::Condtional(conditional, _) if guards.contains(&negate(conditional)) => (),
+            Expr::Condtional(conditional, exprs) => {
+                let existing = out.iter_mut().find_map(|expr| match expr {
+                    Expr::Condtional(other, exprs) if other == conditional => Some(exprs),
+                    _ => None,
+                });
+
+                match existing {
+                    Some(existing) => existing.extend(exprs.iter().cloned()),
+                    None => out.push(expr.clone()),
+                }
+            }
+            _ => out.push(expr.clone()),
+        }
+    }
+}
+
+// an expression may normalize to nothing, or to several expressions that are
+// spliced into the parent list
+fn normalize_expr(expr: Expr, guards: &mut Vec<Conditional>) -> Vec<Expr> {
+    match expr {
+        Expr::Atom(mut atom) => {
+            atom.sort_usedeps();
+            vec![Expr::Atom(atom)]
+        }
+        Expr::UseRequirement(_) => vec![expr],
+        Expr::AllOf(exprs) => normalize_list(&exprs, guards),
+        Expr::Condtional(conditional, exprs) => {
+            guards.push(conditional.clone());
+            let exprs = normalize_list(&exprs, guards);
+            guards.pop();
+
+            if exprs.is_empty() {
+                Vec::new()
+            } else {
+                vec![Expr::Condtional(conditional, exprs)]
+            }
+        }
+        Expr::AnyOf(exprs) => {
+            let mut members = Vec::new();
+
+            for member in normalize_members(&exprs, guards) {
+                match member {
+                    Expr::AnyOf(nested) => members.extend(nested),
+                    member => members.push(member),
+                }
+            }
+
+            let mut seen = Vec::new();
+
+            members.retain(|member| {
+                if seen.contains(member) {
+                    false
+                } else {
+                    seen.push(member.clone());
+                    true
+                }
+            });
+
+            match members.len() {
+                0 => Vec::new(),
+                1 => unwrap_member(members.remove(0)),
+                _ => vec![Expr::AnyOf(members)],
+            }
+        }
+        Expr::OneOf(exprs) => {
+            let mut members = normalize_members(&exprs, guards);
+
+            members.sort_by(compare);
+
+            match members.len() {
+                0 => Vec::new(),
+                1 => unwrap_member(members.remove(0)),
+                _ => vec![Expr::OneOf(members)],
+            }
+        }
+        Expr::AtMostOneOf(exprs) => {
+            let mut members = normalize_members(&exprs, guards);
+
+            members.sort_by(compare);
+
+            if members.len() > 1 {
+                vec![Expr::AtMostOneOf(members)]
+            } else {
+                Vec::new()
+            }
+        }
+    }
+}
+
+// members of a group have to stay single expressions
+fn normalize_members(exprs: &[Expr], guards: &mut Vec<Conditional>) -> Vec<Expr> {
+    let mut members = Vec::new();
+
+    for expr in exprs {
+        let mut normalized = normalize_expr(expr.clone(), guards);
+
+        match normalized.len() {
+            0 => (),
+            1 => members.push(normalized.remove(0)),
+            _ => members.push(Expr::AllOf(normalized)),
+        }
+    }
+
+    members
+}
+
+fn unwrap_member(member: Expr) -> Vec<Expr> {
+    match member {
+        Expr::AllOf(exprs) => exprs,
+        member => vec![member],
+    }
+}
+
+pub(super) fn negate(conditional: &Conditional) -> Conditional {
+    match conditional {
+        Conditional::Positive(flag) => Conditional::Negative(flag.clone()),
+        Conditional::Negative(flag) => Conditional::Positive(flag.clone()),
+    }
+}
+
+// atoms are ordered by package first so that the result reads naturally
+fn compare(a: &Expr, b: &Expr) -> Ordering {
+    match (a, b) {
+        (Expr::Atom(a), Expr::Atom(b)) => a
+            .category()
+            .cmp(b.category())
+            .then_with(|| a.name().cmp(b.name()))
+            .then_with(|| a.cmp(b)),
+        _ => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let table = [
+            ("( cat/b ( cat/a ) ) cat/a", "cat/a cat/b"),
+            (
+                "foo? ( cat/b ) foo? ( cat/a cat/b )",
+                "foo? ( cat/a cat/b )",
+            ),
+            ("foo? ( foo? ( cat/a ) !foo? ( cat/b ) )", "foo? ( cat/a )"),
+            ("|| ( cat/b ) || ( ( cat/c cat/a ) )", "cat/a cat/b cat/c"),
+            (
+                "|| ( cat/b cat/a cat/b || ( cat/c ) )",
+                "|| ( cat/b cat/a cat/c )",
+            ),
+            ("^^ ( b a ) ?? ( c ) ^^ ( d )", "d ^^ ( a b )"),
+            ("cat/a[y,x,y] cat/a[x,y]", "cat/a[x,y]"),
+            (
+                "bar? ( cat/a ) || ( foo? ( cat/b ) ) foo? ( cat/c )",
+                "bar? ( cat/a ) foo? ( cat/b cat/c )",
+            ),
+            (
+                ">=dev-libs/b-2 app-misc/z !!dev-libs/a",
+                "app-misc/z !!dev-libs/a >=dev-libs/b-2",
+            ),
+        ];
+
+        for (input, expected) in table {
+            let spec = input.parse::<DepSpec>().unwrap().normalize();
+
+            assert_eq!(spec.to_string(), expected, "{input}");
+
+            assert_eq!(
+                spec,
+                expected.parse::<DepSpec>().unwrap().normalize(),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_equivalent() {
+        let a = "sys-libs/zlib ssl? ( dev-libs/openssl ) ( dev-libs/libffi )"
+            .parse::<DepSpec>()
+            .unwrap();
+
+        let b = "dev-libs/libffi ssl? ( dev-libs/openssl ssl? ( dev-libs/openssl ) ) sys-libs/zlib"
+            .parse::<DepSpec>()
+            .unwrap();
+
+        assert_ne!(a, b);
+
+        assert_eq!(a.normalize(), b.normalize());
+    }
+}
@@ -1,45 +1,175 @@
 use nom::{
     branch::alt,
-    bytes::complete::tag,
+    bytes::complete::{tag, take_till1},
+    combinator::{cut, eof, opt, verify},
     multi::separated_list1,
     sequence::{delimited, preceded, terminated},
     Parser,
 };
 
 use crate::{
-    atom::parsers::atom, parser_utils::whitespace, useflag::parsers::useflag, ParseResult,
+    atom::parsers::{atom, atom_supported},
+    eapi::Eapi,
+    error::{Expected, ParseError},
+    parser_utils::{context, whitespace},
+    useflag::parsers::useflag,
+    ParseResult,
 };
 
-use super::{Conditional, Expr, UseRequirement};
+use super::{Conditional, DepSpec, Expr, UseRequirement};
 
-pub fn exprs(input: &str) -> ParseResult<Vec<Expr>> {
+pub fn exprs(input: &str) -> ParseResult<'_, Vec<Expr>> {
     separated_list1(whitespace, expr).parse(input)
 }
 
-pub fn expr(input: &str) -> ParseResult<Expr> {
-    let group = || delimited((tag("("), whitespace), exprs, (whitespace, tag(")")));
-    let any_of = preceded((tag("||"), whitespace), group()).map(Expr::AnyOf);
-    let one_of = preceded((tag("^^"), whitespace), group()).map(Expr::OneOf);
-    let all_of = group().map(Expr::AnyOf);
+// a complete dependency specification, which unlike `exprs` may be empty or
+// surrounded by whitespace, as it is in md5-cache and VDB files
+pub fn dep_spec(input: &str) -> ParseResult<'_, DepSpec> {
+    spec(exprs).map(DepSpec).parse_complete(input)
+}
+
+pub fn eapi_dep_spec(eapi: Eapi) -> impl Fn(&str) -> ParseResult<'_, DepSpec> {
+    move |input| spec(eapi_exprs(eapi)).map(DepSpec).parse_complete(input)
+}
 
-    let conditional = (terminated(conditional, whitespace), group())
-        .map(|(condtional, expr)| Expr::Condtional(condtional, expr));
+pub fn expr(input: &str) -> ParseResult<'_, Expr> {
+    let any_of = preceded((tag("||"), whitespace), group(expr)).map(Expr::AnyOf);
+    let one_of = preceded((tag("^^"), whitespace), group(expr)).map(Expr::OneOf);
+    let at_most_one_of = preceded((tag("??"), whitespace), group(expr)).map(Expr::AtMostOneOf);
+    let all_of = group(expr).map(Expr::AllOf);
+
+    let conditional =
+        conditional_group(expr).map(|(condtional, expr)| Expr::Condtional(condtional, expr));
 
     let atom = atom.map(Expr::Atom);
 
     let use_requirement = use_requirement.map(Expr::UseRequirement);
 
-    alt((atom, conditional, use_requirement, any_of, one_of, all_of)).parse_complete(input)
+    context(
+        Expected::Expr,
+        alt((
+            atom,
+            conditional,
+            use_requirement,
+            any_of,
+            one_of,
+            at_most_one_of,
+            all_of,
+        )),
+    )
+    .parse_complete(input)
+}
+
+// a parenthesized, whitespace separated list of items, shared with the other
+// specifications that follow the dependency grammar (SRC_URI, LICENSE...)
+pub(crate) fn group<'a, F>(
+    item: F,
+) -> impl Parser<&'a str, Output = Vec<F::Output>, Error = ParseError<'a>>
+where
+    F: Parser<&'a str, Error = ParseError<'a>>,
+{
+    context(
+        Expected::Group,
+        preceded(
+            (tag("("), whitespace),
+            cut(terminated(
+                separated_list1(whitespace, item),
+                (whitespace, tag(")")),
+            )),
+        ),
+    )
+}
+
+// allows a whitespace separated list to also be empty or surrounded by
+// whitespace, shared by every complete specification in the crate
+pub(crate) fn spec<'a, F, O>(
+    list: F,
+) -> impl Parser<&'a str, Output = Vec<O>, Error = ParseError<'a>>
+where
+    F: Parser<&'a str, Output = Vec<O>, Error = ParseError<'a>>,
+{
+    let empty = eof.map(|_| Vec::new());
+
+    delimited(opt(whitespace), alt((list, empty)), opt(whitespace))
 }
 
-fn conditional(input: &str) -> ParseResult<Conditional> {
+// any word that could not be mistaken for a group or a conditional, shared
+// with the specifications made up of plain words (SRC_URI, RESTRICT...).
+// `reserved` are further words the specification gives a meaning to
+pub(crate) fn token<'a>(
+    reserved: &'static [&'static str],
+) -> impl Parser<&'a str, Output = &'a str, Error = ParseError<'a>> {
+    verify(
+        take_till1(|c: char| c.is_ascii_whitespace()),
+        move |token: &str| {
+            !matches!(token, "(" | ")" | "||")
+                && !reserved.contains(&token)
+                && !token.ends_with('?')
+        },
+    )
+}
+
+pub(crate) fn conditional_group<'a, F>(
+    item: F,
+) -> impl Parser<&'a str, Output = (Conditional, Vec<F::Output>), Error = ParseError<'a>>
+where
+    F: Parser<&'a str, Error = ParseError<'a>>,
+{
+    (terminated(conditional, whitespace), group(item))
+}
+
+// dependency specifications (DEPEND, RDEPEND...) may only contain atoms
+// supported by the EAPI, and never use requirements, ^^ or ?? groups
+pub fn eapi_exprs(eapi: Eapi) -> impl Fn(&str) -> ParseResult<'_, Vec<Expr>> {
+    move |input| {
+        verify(exprs, |exprs: &[Expr]| {
+            exprs.iter().all(|expr| dependency_supported(eapi, expr))
+        })
+        .parse_complete(input)
+    }
+}
+
+pub fn eapi_required_use(eapi: Eapi) -> impl Fn(&str) -> ParseResult<'_, Vec<Expr>> {
+    move |input| {
+        verify(exprs, |exprs: &[Expr]| {
+            eapi.has_required_use() && exprs.iter().all(|expr| required_use_supported(eapi, expr))
+        })
+        .parse_complete(input)
+    }
+}
+
+fn dependency_supported(eapi: Eapi, expr: &Expr) -> bool {
+    match expr {
+        Expr::Atom(atom) => atom_supported(eapi, atom),
+        Expr::UseRequirement(_) | Expr::OneOf(_) | Expr::AtMostOneOf(_) => false,
+        Expr::AllOf(exprs) | Expr::AnyOf(exprs) | Expr::Condtional(_, exprs) => {
+            exprs.iter().all(|expr| dependency_supported(eapi, expr))
+        }
+    }
+}
+
+fn required_use_supported(eapi: Eapi, expr: &Expr) -> bool {
+    match expr {
+        Expr::Atom(_) => false,
+        Expr::UseRequirement(_) => true,
+        Expr::OneOf(_) if !eapi.has_one_of() => false,
+        Expr::AtMostOneOf(_) if !eapi.has_at_most_one_of() => false,
+        Expr::AllOf(exprs)
+        | Expr::AnyOf(exprs)
+        | Expr::OneOf(exprs)
+        | Expr::AtMostOneOf(exprs)
+        | Expr::Condtional(_, exprs) => exprs.iter().all(|expr| required_use_supported(eapi, expr)),
+    }
+}
+
+fn conditional(input: &str) -> ParseResult<'_, Conditional> {
     let negative = delimited(tag("!"), useflag, tag("?")).map(Conditional::Negative);
     let positive = terminated(useflag, tag("?")).map(Conditional::Positive);
 
-    alt((negative, positive)).parse_complete(input)
+    context(Expected::Conditional, alt((negative, positive))).parse_complete(input)
 }
 
-fn use_requirement(input: &str) -> ParseResult<UseRequirement> {
+fn use_requirement(input: &str) -> ParseResult<'_, UseRequirement> {
     let negative = preceded(tag("!"), useflag).map(UseRequirement::Negative);
     let positive = useflag.map(UseRequirement::Positive);
 
@@ -53,7 +183,8 @@ mod tests {
 
     #[test]
     fn test_simple_expr() {
-        let input = r#"cat/pkg use? ( cat/pkg || ( cat/pkg cat/pkg ) ^^ ( cat/pkg cat/pkg ) )"#;
+        let input =
+            r#"cat/pkg use? ( cat/pkg || ( cat/pkg cat/pkg ) ^^ ( cat/pkg cat/pkg ) ?? ( a b ) )"#;
 
         let (_, exprs) = exprs(input).unwrap();
 
@@ -67,7 +198,125 @@ mod tests {
             if
             matches!(exprs[0], Expr::Atom(_)) &&
             matches!(exprs[1], Expr::AnyOf(_)) &&
-            matches!(exprs[2], Expr::OneOf(_))
+            matches!(exprs[2], Expr::OneOf(_)) &&
+            matches!(exprs[3], Expr::AtMostOneOf(_))
         ));
     }
+
+    #[test]
+    fn test_all_of_group() {
+        let (_, exprs) = exprs("( cat/a cat/b ) || ( ( cat/c cat/d ) cat/e )").unwrap();
+
+        assert!(matches!(&exprs[0], Expr::AllOf(group) if group.len() == 2));
+
+        assert!(matches!(
+            &exprs[1],
+            Expr::AnyOf(group) if matches!(&group[0], Expr::AllOf(inner) if inner.len() == 2)
+        ));
+    }
+
+    #[test]
+    fn test_eapi_exprs() {
+        let table = [
+            ("cat/pkg foo? ( cat/dep )", Some(Eapi::Zero)),
+            ("!foo? ( cat/pkg ) || ( cat/a cat/b )", Some(Eapi::Zero)),
+            ("|| ( cat/a:1 cat/a:2 )", Some(Eapi::One)),
+            ("foo? ( cat/pkg[bar] !!cat/blocker )", Some(Eapi::Two)),
+            ("|| ( cat/pkg[bar(-)] cat/other )", Some(Eapi::Four)),
+            ("foo? ( || ( cat/pkg:= ) )", Some(Eapi::Five)),
+            ("cat/pkg::gentoo", None),
+            ("^^ ( cat/a cat/b )", None),
+            ("?? ( cat/a cat/b )", None),
+            ("foo? ( bar )", None),
+        ];
+
+        for (input, since) in table {
+            for eapi in Eapi::ALL {
+                let expected = since.is_some_and(|since| eapi >= since);
+
+                assert_eq!(
+                    eapi_exprs(eapi)(input).is_ok_and(|(rest, _)| rest.is_empty()),
+                    expected,
+                    "{input} in EAPI {eapi}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_eapi_required_use() {
+        let table = [
+            ("foo? ( bar ) || ( a b )", Some(Eapi::Four)),
+            ("^^ ( a b ) !foo? ( !bar )", Some(Eapi::Four)),
+            ("?? ( a b ) foo? ( ?? ( c d ) )", Some(Eapi::Five)),
+            ("foo? ( cat/pkg )", None),
+        ];
+
+        for (input, since) in table {
+            for eapi in Eapi::ALL {
+                let expected = since.is_some_and(|since| eapi >= since);
+
+                assert_eq!(
+                    eapi_required_use(eapi)(input).is_ok_and(|(rest, _)| rest.is_empty()),
+                    expected,
+                    "{input} in EAPI {eapi}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_dep_spec() {
+        let input = "\tcat/pkg\n\tfoo? ( cat/dep )\n";
+
+        let spec = input.parse::<DepSpec>().unwrap();
+
+        assert_eq!(spec.exprs().len(), 2);
+
+        assert!("".parse::<DepSpec>().unwrap().is_empty());
+
+        assert!(" \n".parse::<DepSpec>().unwrap().is_empty());
+
+        let error = "cat/pkg foo? ( cat/dep ) )".parse::<DepSpec>().unwrap_err();
+
+        assert_eq!(error.offset(), 25);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let inputs = [
+            // dev-lang/rust
+            "llvm_targets_AArch64? ( llvm-core/llvm:18[llvm_targets_AArch64] ) >=app-arch/xz-utils-5.2 net-misc/curl:=[http2,ssl] sys-libs/zlib:= dev-libs/openssl:0= system-llvm? ( llvm-core/llvm:18= ) !system-llvm? ( || ( dev-build/cmake[-nls] >=dev-build/cmake-3.13.4 ) )",
+            // media-video/ffmpeg
+            "alsa? ( >=media-libs/alsa-lib-1.0.27.2[abi_x86_32(-)?,abi_x86_64(-)?] ) openssl? ( >=dev-libs/openssl-1.0.1h-r2:0=[abi_x86_32(-)?,abi_x86_64(-)?] ) !openssl? ( gnutls? ( >=net-libs/gnutls-2.12.23-r6:=[abi_x86_32(-)?,abi_x86_64(-)?] ) ) !media-video/qt-faststart",
+            // dev-lang/python
+            "|| ( ( dev-libs/libffi:= virtual/libcrypt:= ) dev-libs/libffi-compat ) bluetooth? ( net-wireless/bluez ) !!<sys-apps/sandbox-2.21",
+            // a REQUIRED_USE string
+            "^^ ( python_targets_python3_12 python_targets_python3_13 ) gui? ( || ( qt5 qt6 ) !minimal ) ( a b )",
+            // media-libs/mesa
+            "d3d9? ( ?? ( video_cards_i915 video_cards_r300 ) ) vulkan? ( video_cards_radeonsi? ( llvm ) )",
+        ];
+
+        for input in inputs {
+            let spec = input.parse::<DepSpec>().unwrap();
+
+            assert_eq!(spec.to_string(), input);
+
+            let pretty = format!("{spec:#}");
+
+            assert_eq!(pretty.parse::<DepSpec>().unwrap().to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_pretty_print() {
+        let input = "cat/a foo? ( cat/b || ( cat/c !bar? ( cat/d ) ) )";
+
+        let spec = input.parse::<DepSpec>().unwrap();
+
+        assert_eq!(
+            format!("{spec:#}"),
+            "cat/a\nfoo? (\n\tcat/b\n\t|| (\n\t\tcat/c\n\t\t!bar? (\n\t\t\tcat/d\n\t\t)\n\t)\n)"
+        );
+    }
 }
@@ -0,0 +1,223 @@
+use crate::atom::Atom;
+
+use super::{Conditional, DepSpec, Expr, UseRequirement};
+
+// walks a dependency tree, the conditionals passed to each method are the ones
+// guarding the expression, outermost first
+pub trait Visitor<'a> {
+    // called for every expression before its children are visited, returning
+    // false skips the children
+    fn enter(&mut self, _expr: &'a Expr, _conditionals: &[&'a Conditional]) -> bool {
+        true
+    }
+
+    fn visit_atom(&mut self, _atom: &'a Atom, _conditionals: &[&'a Conditional]) {}
+
+    fn visit_use_requirement(
+        &mut self,
+        _requirement: &'a UseRequirement,
+        _conditionals: &[&'a Conditional],
+    ) {
+    }
+}
+
+// like `Visitor` but allows the tree to be rewritten in place, the guarding
+// conditionals are copies since the tree they came from is borrowed mutably
+pub trait VisitorMut {
+    fn enter_mut(&mut self, _expr: &mut Expr, _conditionals: &[Conditional]) -> bool {
+        true
+    }
+
+    fn visit_atom_mut(&mut self, _atom: &mut Atom, _conditionals: &[Conditional]) {}
+
+    fn visit_use_requirement_mut(
+        &mut self,
+        _requirement: &mut UseRequirement,
+        _conditionals: &[Conditional],
+    ) {
+    }
+}
+
+impl Expr {
+    pub fn visit<'a, V: Visitor<'a> + ?Sized>(&'a self, visitor: &mut V) {
+        walk(self, visitor, &mut Vec::new());
+    }
+
+    pub fn visit_mut<V: VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        walk_mut(self, visitor, &mut Vec::new());
+    }
+}
+
+impl DepSpec {
+    pub fn visit<'a, V: Visitor<'a> + ?Sized>(&'a self, visitor: &mut V) {
+        let mut conditionals = Vec::new();
+
+        for expr in &self.0 {
+            walk(expr, visitor, &mut conditionals);
+        }
+    }
+
+    pub fn visit_mut<V: VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        let mut conditionals = Vec::new();
+
+        for expr in &mut self.0 {
+            walk_mut(expr, visitor, &mut conditionals);
+        }
+    }
+
+    // every atom along with the conditionals guarding it
+    pub fn guarded_atoms(&self) -> impl Iterator<Item = (&Atom, Vec<&Conditional>)> {
+        struct Collect<'a>(Vec<(&'a Atom, Vec<&'a Conditional>)>);
+
+        impl<'a> Visitor<'a> for Collect<'a> {
+            fn visit_atom(&mut self, atom: &'a Atom, conditionals: &[&'a Conditional]) {
+                self.0.push((atom, conditionals.to_vec()));
+            }
+        }
+
+        let mut collect = Collect(Vec::new());
+
+        self.visit(&mut collect);
+
+        collect.0.into_iter()
+    }
+}
+
+fn walk<'a, V: Visitor<'a> + ?Sized>(
+    expr: &'a Expr,
+    visitor: &mut V,
+    conditionals: &mut Vec<&'a Conditional>,
+) {
+    if !visitor.enter(expr, conditionals) {
+        return;
+    }
+
+    match expr {
+        Expr::Atom(atom) => visitor.visit_atom(atom, conditionals),
+        Expr::UseRequirement(requirement) => {
+            visitor.visit_use_requirement(requirement, conditionals)
+        }
+        Expr::AllOf(exprs) | Expr::AnyOf(exprs) | Expr::OneOf(exprs) | Expr::AtMostOneOf(exprs) => {
+            for expr in exprs {
+                walk(expr, visitor, conditionals);
+            }
+        }
+        Expr::Condtional(conditional, exprs) => {
+            conditionals.push(conditional);
+
+            for expr in exprs {
+                walk(expr, visitor, conditionals);
+            }
+
+            conditionals.pop();
+        }
+    }
+}
+
+fn walk_mut<V: VisitorMut + ?Sized>(
+    expr: &mut Expr,
+    visitor: &mut V,
+    conditionals: &mut Vec<Conditional>,
+) {
+    if !visitor.enter_mut(expr, conditionals) {
+        return;
+    }
+
+    match expr {
+        Expr::Atom(atom) => visitor.visit_atom_mut(atom, conditionals),
+        Expr::UseRequirement(requirement) => {
+            visitor.visit_use_requirement_mut(requirement, conditionals)
+        }
+        Expr::AllOf(exprs) | Expr::AnyOf(exprs) | Expr::OneOf(exprs) | Expr::AtMostOneOf(exprs) => {
+            for expr in exprs {
+                walk_mut(expr, visitor, conditionals);
+            }
+        }
+        Expr::Condtional(conditional, exprs) => {
+            conditionals.push(conditional.clone());
+
+            for expr in exprs {
+                walk_mut(expr, visitor, conditionals);
+            }
+
+            conditionals.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    #[test]
+    fn test_guarded_atoms() {
+        let spec = "dev-libs/glib gui? ( || ( dev-qt/qtbase:6 dev-qt/qtgui:5 ) ) !minimal? ( designer? ( dev-qt/qtbase:6[widgets] ) )"
+            .parse::<DepSpec>()
+            .unwrap();
+
+        let flags = spec
+            .guarded_atoms()
+            .filter(|(atom, _)| atom.category().get() == "dev-qt" && atom.name().get() == "qtbase")
+            .map(|(_, conditionals)| {
+                conditionals
+                    .iter()
+                    .map(|conditional| conditional.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(flags, [vec!["gui?"], vec!["!minimal?", "designer?"]]);
+    }
+
+    #[test]
+    fn test_visitor() {
+        struct Flags(BTreeSet<String>);
+
+        impl<'a> Visitor<'a> for Flags {
+            // skip anything inside of any-of groups
+            fn enter(&mut self, expr: &'a Expr, _: &[&'a Conditional]) -> bool {
+                !matches!(expr, Expr::AnyOf(_))
+            }
+
+            fn visit_use_requirement(
+                &mut self,
+                requirement: &'a UseRequirement,
+                _: &[&'a Conditional],
+            ) {
+                self.0.insert(requirement.to_string());
+            }
+        }
+
+        let spec = "a !b foo? ( c ) || ( d e )".parse::<DepSpec>().unwrap();
+
+        let mut flags = Flags(BTreeSet::new());
+
+        spec.visit(&mut flags);
+
+        assert_eq!(flags.0.into_iter().collect::<Vec<_>>(), ["!b", "a", "c"]);
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        struct StripUseDeps;
+
+        impl VisitorMut for StripUseDeps {
+            fn visit_atom_mut(&mut self, atom: &mut Atom, conditionals: &[Conditional]) {
+                if conditionals.is_empty() {
+                    *atom = format!("{}/{}", atom.category(), atom.name())
+                        .parse()
+                        .unwrap();
+                }
+            }
+        }
+
+        let mut spec = "cat/a[x] foo? ( cat/b[y] )".parse::<DepSpec>().unwrap();
+
+        spec.visit_mut(&mut StripUseDeps);
+
+        assert_eq!(spec.to_string(), "cat/a foo? ( cat/b[y] )");
+    }
+}
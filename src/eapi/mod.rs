@@ -0,0 +1,112 @@
+use core::{fmt, str::FromStr, write};
+use std::fmt::Display;
+
+use crate::{error::Error, parser_utils::parse_all};
+
+pub mod parsers;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Eapi {
+    Zero,
+    One,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+impl Eapi {
+    pub const ALL: [Eapi; 9] = [
+        Eapi::Zero,
+        Eapi::One,
+        Eapi::Two,
+        Eapi::Three,
+        Eapi::Four,
+        Eapi::Five,
+        Eapi::Six,
+        Eapi::Seven,
+        Eapi::Eight,
+    ];
+
+    pub fn has_slot_deps(self) -> bool {
+        self >= Eapi::One
+    }
+
+    pub fn has_src_uri_arrows(self) -> bool {
+        self >= Eapi::Two
+    }
+
+    pub fn has_strong_blockers(self) -> bool {
+        self >= Eapi::Two
+    }
+
+    pub fn has_use_deps(self) -> bool {
+        self >= Eapi::Two
+    }
+
+    pub fn has_use_dep_defaults(self) -> bool {
+        self >= Eapi::Four
+    }
+
+    pub fn has_required_use(self) -> bool {
+        self >= Eapi::Four
+    }
+
+    pub fn has_one_of(self) -> bool {
+        self >= Eapi::Four
+    }
+
+    pub fn has_at_most_one_of(self) -> bool {
+        self >= Eapi::Five
+    }
+
+    pub fn has_slot_operators(self) -> bool {
+        self >= Eapi::Five
+    }
+
+    pub fn has_subslots(self) -> bool {
+        self >= Eapi::Five
+    }
+
+    pub fn has_bdepend(self) -> bool {
+        self >= Eapi::Seven
+    }
+
+    pub fn has_idepend(self) -> bool {
+        self >= Eapi::Eight
+    }
+
+    pub fn has_selective_uri_restrictions(self) -> bool {
+        self >= Eapi::Eight
+    }
+}
+
+impl FromStr for Eapi {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::eapi)
+    }
+}
+
+impl Display for Eapi {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let n = match self {
+            Self::Zero => 0,
+            Self::One => 1,
+            Self::Two => 2,
+            Self::Three => 3,
+            Self::Four => 4,
+            Self::Five => 5,
+            Self::Six => 6,
+            Self::Seven => 7,
+            Self::Eight => 8,
+        };
+
+        write!(f, "{}", n)
+    }
+}
@@ -0,0 +1,55 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{not, value},
+    sequence::terminated,
+    AsChar, Parser,
+};
+
+use crate::{
+    error::Expected,
+    parser_utils::{context, take_1_if},
+    ParseResult,
+};
+
+use super::Eapi;
+
+pub fn eapi(input: &str) -> ParseResult<'_, Eapi> {
+    let eapi = terminated(
+        alt((
+            value(Eapi::Zero, tag("0")),
+            value(Eapi::One, tag("1")),
+            value(Eapi::Two, tag("2")),
+            value(Eapi::Three, tag("3")),
+            value(Eapi::Four, tag("4")),
+            value(Eapi::Five, tag("5")),
+            value(Eapi::Six, tag("6")),
+            value(Eapi::Seven, tag("7")),
+            value(Eapi::Eight, tag("8")),
+        )),
+        not(take_1_if(|c: char| {
+            c.is_alphanum() || matches!(c, '+' | '_' | '.' | '-')
+        })),
+    );
+
+    context(Expected::Eapi, eapi).parse_complete(input)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_eapi() {
+        for eapi in Eapi::ALL {
+            assert_eq!(self::eapi(&eapi.to_string()).unwrap().1, eapi);
+        }
+
+        assert!(eapi("9").is_err());
+
+        assert!(eapi("80").is_err());
+
+        assert!(eapi("5-progress").is_err());
+    }
+}
@@ -0,0 +1,303 @@
+use core::{fmt, iter::ExactSizeIterator, write};
+use std::fmt::Display;
+
+use nom::error::{ErrorKind, FromExternalError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expected {
+    Atom,
+    Blocker,
+    VersionOperator,
+    Category,
+    Name,
+    Version,
+    VersionSuffix,
+    Revision,
+    Slot,
+    Subslot,
+    Repo,
+    Cpv,
+    UseFlag,
+    UseDep,
+    Eapi,
+    Expr,
+    Conditional,
+    Group,
+    Uri,
+    License,
+    Token,
+    Number,
+    Content,
+    Eof,
+}
+
+// the error type used by all of the parsers in this crate, it borrows the
+// input so it is cheap to build while backtracking, convert it into an
+// `Error` to get something that can be reported
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError<'a> {
+    input: &'a str,
+    kind: ErrorKind,
+    context: Vec<(&'a str, Expected)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    offset: usize,
+    line: usize,
+    column: usize,
+    source_line: String,
+    expected: Option<Expected>,
+    context: Vec<Expected>,
+}
+
+impl<'a> ParseError<'a> {
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub(crate) fn push(mut self, input: &'a str, expected: Expected) -> Self {
+        self.context.push((input, expected));
+        self
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a str> for ParseError<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        Self {
+            input,
+            kind,
+            context: Vec::new(),
+        }
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    // keep whichever branch made it furthest into the input, it is almost
+    // always the one the user meant to write
+    fn or(self, other: Self) -> Self {
+        if self.input.len() < other.input.len() {
+            self
+        } else {
+            other
+        }
+    }
+}
+
+impl<'a, E> FromExternalError<&'a str, E> for ParseError<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, _: E) -> Self {
+        <Self as nom::error::ParseError<&'a str>>::from_error_kind(input, kind)
+    }
+}
+
+impl Error {
+    pub fn new(source: &str, error: nom::Err<ParseError<'_>>) -> Self {
+        let (remaining, context) = match error {
+            nom::Err::Error(e) | nom::Err::Failure(e) => (e.input, e.context),
+            nom::Err::Incomplete(_) => ("", Vec::new()),
+        };
+
+        let offset = source.len().saturating_sub(remaining.len());
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+
+        Self {
+            offset,
+            line: source[..offset].matches('\n').count() + 1,
+            column: source[line_start..offset].chars().count() + 1,
+            source_line: source[line_start..]
+                .split('\n')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            expected: context.first().map(|(_, expected)| *expected),
+            context: context
+                .iter()
+                .rev()
+                .map(|(_, expected)| *expected)
+                .collect(),
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn source_line(&self) -> &str {
+        self.source_line.as_str()
+    }
+
+    pub fn expected(&self) -> Option<Expected> {
+        self.expected
+    }
+
+    // outermost construct first
+    pub fn context(&self) -> impl ExactSizeIterator<Item = Expected> + '_ {
+        self.context.iter().copied()
+    }
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Atom => write!(f, "atom"),
+            Self::Blocker => write!(f, "blocker"),
+            Self::VersionOperator => write!(f, "version operator"),
+            Self::Category => write!(f, "category"),
+            Self::Name => write!(f, "package name"),
+            Self::Version => write!(f, "version"),
+            Self::VersionSuffix => write!(f, "version suffix"),
+            Self::Revision => write!(f, "revision"),
+            Self::Slot => write!(f, "slot"),
+            Self::Subslot => write!(f, "subslot"),
+            Self::Repo => write!(f, "repository name"),
+            Self::Cpv => write!(f, "category/package-version"),
+            Self::UseFlag => write!(f, "USE flag"),
+            Self::UseDep => write!(f, "USE dependency"),
+            Self::Eapi => write!(f, "EAPI"),
+            Self::Expr => write!(f, "expression"),
+            Self::Conditional => write!(f, "USE conditional"),
+            Self::Group => write!(f, "group"),
+            Self::Uri => write!(f, "URI"),
+            Self::License => write!(f, "license"),
+            Self::Token => write!(f, "token"),
+            Self::Number => write!(f, "number"),
+            Self::Content => write!(f, "CONTENTS entry"),
+            Self::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+// renders a caret diagnostic pointing at the offending column, for example
+//
+// error: expected slot at line 1, column 9
+//   |
+// 1 | cat/pkg:[foo]
+//   |         ^
+//   = while parsing atom > slot
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.expected {
+            Some(expected) => write!(f, "error: expected {}", expected)?,
+            None => write!(f, "error: unexpected input")?,
+        }
+
+        writeln!(f, " at line {}, column {}", self.line, self.column)?;
+
+        let gutter = self.line.to_string();
+        let padding = " ".repeat(gutter.len());
+
+        writeln!(f, "{} |", padding)?;
+        writeln!(f, "{} | {}", gutter, self.source_line)?;
+
+        // tabs are copied from the source line so the caret lines up with
+        // it however wide the terminal renders them
+        let indent = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        write!(f, "{} | {}^", padding, indent)?;
+
+        if !self.context.is_empty() {
+            write!(f, "\n{} = while parsing ", padding)?;
+
+            for (i, expected) in self.context.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " > ")?;
+                }
+
+                write!(f, "{}", expected)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+
+    use nom::{combinator::eof, sequence::terminated, Parser};
+
+    use crate::{atom::parsers::atom, depend::parsers::exprs};
+
+    use super::*;
+
+    #[test]
+    fn test_atom_error() {
+        let input = "dev-lang/rust:[llvm]";
+
+        let error = Error::new(input, atom(input).unwrap_err());
+
+        assert_eq!(error.offset(), 14);
+
+        assert_eq!((error.line(), error.column()), (1, 15));
+
+        assert_eq!(error.expected(), Some(Expected::Slot));
+
+        assert_eq!(
+            error.context().collect::<Vec<_>>(),
+            [Expected::Atom, Expected::Slot]
+        );
+
+        assert_eq!(
+            error.to_string(),
+            "error: expected slot at line 1, column 15
+  |
+1 | dev-lang/rust:[llvm]
+  |               ^
+  = while parsing atom > slot"
+        );
+    }
+
+    #[test]
+    fn test_multiline_error() {
+        let input = "dev-lang/rust\nfoo? (\n\tdev-libs/openssl[-bindist,]\n)";
+
+        let error = Error::new(
+            input,
+            terminated(exprs, eof).parse_complete(input).unwrap_err(),
+        );
+
+        assert_eq!((error.line(), error.column()), (3, 28));
+
+        assert_eq!(error.source_line(), "\tdev-libs/openssl[-bindist,]");
+
+        assert_eq!(error.expected(), Some(Expected::UseFlag));
+
+        assert_eq!(
+            error.context().collect::<Vec<_>>(),
+            [
+                Expected::Expr,
+                Expected::Group,
+                Expected::Expr,
+                Expected::Atom,
+                Expected::UseDep,
+                Expected::UseFlag
+            ]
+        );
+
+        assert_eq!(
+            error.to_string().lines().nth(3),
+            Some("  | \t                          ^")
+        );
+    }
+}
@@ -1,7 +1,17 @@
 pub mod atom;
 pub mod depend;
+pub mod eapi;
+pub mod error;
+pub mod license;
 pub mod parser_utils;
+pub mod required_use;
+pub mod restrict;
+#[cfg(feature = "serde")]
+pub mod serde_utils;
+pub mod src_uri;
+#[cfg(test)]
+mod test_utils;
 pub mod useflag;
 pub mod vdb;
 
-pub type ParseResult<'a, T> = nom::IResult<&'a str, T>;
+pub type ParseResult<'a, T> = nom::IResult<&'a str, T, error::ParseError<'a>>;
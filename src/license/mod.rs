@@ -0,0 +1,462 @@
+pub mod parsers;
+
+use core::{
+    fmt::{self, Display},
+    iter::ExactSizeIterator,
+    str::FromStr,
+    write,
+};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    depend::{write_group, Conditional},
+    error::Error,
+    parser_utils::parse_all,
+    useflag::UseFlag,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct License(String);
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expr {
+    License(License),
+    AllOf(Vec<Expr>),
+    AnyOf(Vec<Expr>),
+    Conditional(Conditional, Vec<Expr>),
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LicenseSpec(Vec<Expr>);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pattern {
+    All,
+    License(License),
+    Group(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Token {
+    Accept(Pattern),
+    Reject(Pattern),
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AcceptLicense(Vec<Token>);
+
+// group names mapped to their members, which are either license names or
+// other groups prefixed with @
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LicenseGroups(HashMap<String, Vec<String>>);
+
+impl License {
+    pub fn get(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl LicenseSpec {
+    pub fn exprs(&self) -> impl ExactSizeIterator<Item = &Expr> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn accepted(
+        &self,
+        useflags: &HashSet<UseFlag>,
+        accept: &AcceptLicense,
+        groups: &LicenseGroups,
+    ) -> bool {
+        self.rejected(useflags, accept, groups).is_empty()
+    }
+
+    // the licenses that keep the package from being accepted, for an any-of
+    // group where nothing is accepted that is every license in the group
+    pub fn rejected(
+        &self,
+        useflags: &HashSet<UseFlag>,
+        accept: &AcceptLicense,
+        groups: &LicenseGroups,
+    ) -> Vec<&License> {
+        let mut rejected = Vec::new();
+
+        for expr in &self.0 {
+            rejected.extend(rejected_by(expr, useflags, accept, groups));
+        }
+
+        rejected
+    }
+}
+
+impl AcceptLicense {
+    pub fn tokens(&self) -> impl ExactSizeIterator<Item = &Token> {
+        self.0.iter()
+    }
+
+    // later tokens override earlier ones, so the last token that matches the
+    // license decides, and nothing is accepted unless some token says so
+    pub fn accepts(&self, license: &License, groups: &LicenseGroups) -> bool {
+        for token in self.0.iter().rev() {
+            let (pattern, accept) = match token {
+                Token::Accept(pattern) => (pattern, true),
+                Token::Reject(pattern) => (pattern, false),
+            };
+
+            let matches = match pattern {
+                Pattern::All => true,
+                Pattern::License(other) => other == license,
+                Pattern::Group(group) => groups.contains(group, license),
+            };
+
+            if matches {
+                return accept;
+            }
+        }
+
+        false
+    }
+}
+
+impl LicenseGroups {
+    pub fn members(&self, group: &str) -> Option<&[String]> {
+        self.0.get(group).map(Vec::as_slice)
+    }
+
+    // whether the group contains the license, directly or through one of its
+    // nested groups
+    pub fn contains(&self, group: &str, license: &License) -> bool {
+        let mut seen = HashSet::new();
+
+        self.contains_inner(group, license, &mut seen)
+    }
+
+    // every license in the group with nested groups expanded
+    pub fn expand<'a>(&'a self, group: &'a str) -> HashSet<&'a str> {
+        let mut seen = HashSet::new();
+        let mut licenses = HashSet::new();
+
+        self.expand_inner(group, &mut seen, &mut licenses);
+
+        licenses
+    }
+
+    fn contains_inner<'a>(
+        &'a self,
+        group: &'a str,
+        license: &License,
+        seen: &mut HashSet<&'a str>,
+    ) -> bool {
+        if !seen.insert(group) {
+            return false;
+        }
+
+        self.members(group)
+            .unwrap_or_default()
+            .iter()
+            .any(|member| match member.strip_prefix('@') {
+                Some(group) => self.contains_inner(group, license, seen),
+                None => member == license.get(),
+            })
+    }
+
+    fn expand_inner<'a>(
+        &'a self,
+        group: &'a str,
+        seen: &mut HashSet<&'a str>,
+        licenses: &mut HashSet<&'a str>,
+    ) {
+        if !seen.insert(group) {
+            return;
+        }
+
+        for member in self.members(group).unwrap_or_default() {
+            match member.strip_prefix('@') {
+                Some(group) => self.expand_inner(group, seen, licenses),
+                None => {
+                    licenses.insert(member.as_str());
+                }
+            }
+        }
+    }
+}
+
+impl From<Vec<Expr>> for LicenseSpec {
+    fn from(exprs: Vec<Expr>) -> Self {
+        Self(exprs)
+    }
+}
+
+impl From<LicenseSpec> for Vec<Expr> {
+    fn from(spec: LicenseSpec) -> Self {
+        spec.0
+    }
+}
+
+impl FromStr for License {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::license)
+    }
+}
+
+impl FromStr for LicenseSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::license_spec)
+    }
+}
+
+impl FromStr for AcceptLicense {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::accept_license)
+    }
+}
+
+impl FromStr for LicenseGroups {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::license_groups)
+    }
+}
+
+impl Display for License {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::License(license) => write!(f, "{}", license),
+            Self::AllOf(exprs) => write_group(exprs, None, f, |expr, _, f| write!(f, "{}", expr)),
+            Self::AnyOf(exprs) => {
+                write!(f, "|| ")?;
+                write_group(exprs, None, f, |expr, _, f| write!(f, "{}", expr))
+            }
+            Self::Conditional(conditional, exprs) => {
+                write!(f, "{} ", conditional)?;
+                write_group(exprs, None, f, |expr, _, f| write!(f, "{}", expr))
+            }
+        }
+    }
+}
+
+impl Display for LicenseSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, expr) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", expr)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::All => write!(f, "*"),
+            Self::License(license) => write!(f, "{}", license),
+            Self::Group(group) => write!(f, "@{}", group),
+        }
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Accept(pattern) => write!(f, "{}", pattern),
+            Self::Reject(pattern) => write!(f, "-{}", pattern),
+        }
+    }
+}
+
+impl Display for AcceptLicense {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, token) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", token)?;
+        }
+
+        Ok(())
+    }
+}
+
+// conditionals that are not enabled never reject anything, and neither do
+// empty any-of groups
+fn rejected_by<'a>(
+    expr: &'a Expr,
+    useflags: &HashSet<UseFlag>,
+    accept: &AcceptLicense,
+    groups: &LicenseGroups,
+) -> Vec<&'a License> {
+    let all_of = |exprs: &'a [Expr]| {
+        exprs
+            .iter()
+            .flat_map(|expr| rejected_by(expr, useflags, accept, groups))
+            .collect::<Vec<_>>()
+    };
+
+    match expr {
+        Expr::License(license) if accept.accepts(license, groups) => Vec::new(),
+        Expr::License(license) => vec![license],
+        Expr::AllOf(exprs) => all_of(exprs),
+        Expr::Conditional(conditional, exprs) if conditional.enabled(useflags) => all_of(exprs),
+        Expr::Conditional(_, _) => Vec::new(),
+        Expr::AnyOf(exprs) => {
+            let members = exprs
+                .iter()
+                .filter(|expr| match expr {
+                    Expr::Conditional(conditional, _) => conditional.enabled(useflags),
+                    _ => true,
+                })
+                .map(|expr| rejected_by(expr, useflags, accept, groups))
+                .collect::<Vec<_>>();
+
+            if members.is_empty() || members.iter().any(Vec::is_empty) {
+                Vec::new()
+            } else {
+                members.into_iter().flatten().collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test_utils::useflags;
+
+    use super::*;
+
+    fn groups() -> LicenseGroups {
+        "GPL-COMPATIBLE Apache-2.0 GPL-2 GPL-3 LGPL-2.1 MIT\nFREE @GPL-COMPATIBLE @MISC-FREE\nMISC-FREE public-domain\nEULA NVIDIA-r2\n"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_accepts() {
+        let groups = groups();
+
+        let table = [
+            ("-* @FREE", "MIT", true),
+            ("-* @FREE", "public-domain", true),
+            ("-* @FREE", "NVIDIA-r2", false),
+            ("* -@EULA", "NVIDIA-r2", false),
+            ("* -@EULA", "unknown", true),
+            ("-* @FREE -MIT", "MIT", false),
+            ("-* @FREE -MIT", "GPL-2", true),
+            ("@FREE -*", "MIT", false),
+            ("", "MIT", false),
+        ];
+
+        for (accept, license, expected) in table {
+            let accept = accept.parse::<AcceptLicense>().unwrap();
+
+            assert_eq!(
+                accept.accepts(&license.parse().unwrap(), &groups),
+                expected,
+                "{accept} {license}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejected() {
+        let groups = groups();
+        let accept = "-* @FREE".parse::<AcceptLicense>().unwrap();
+
+        let table = [
+            ("MIT GPL-2", &[][..], &[][..]),
+            ("MIT NVIDIA-r2", &[][..], &["NVIDIA-r2"][..]),
+            ("|| ( NVIDIA-r2 MIT )", &[][..], &[][..]),
+            (
+                "|| ( NVIDIA-r2 unrar )",
+                &[][..],
+                &["NVIDIA-r2", "unrar"][..],
+            ),
+            ("MIT bindist? ( NVIDIA-r2 )", &[][..], &[][..]),
+            (
+                "MIT bindist? ( NVIDIA-r2 )",
+                &["bindist"][..],
+                &["NVIDIA-r2"][..],
+            ),
+            ("|| ( foo? ( NVIDIA-r2 ) )", &[][..], &[][..]),
+        ];
+
+        for (input, flags, expected) in table {
+            let spec = input.parse::<LicenseSpec>().unwrap();
+
+            let rejected = spec.rejected(&useflags(flags), &accept, &groups);
+
+            assert_eq!(
+                rejected
+                    .iter()
+                    .map(|license| license.get())
+                    .collect::<Vec<_>>(),
+                expected,
+                "{input} with {flags:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        let groups = groups();
+
+        let mut licenses = groups.expand("FREE").into_iter().collect::<Vec<_>>();
+
+        licenses.sort();
+
+        assert_eq!(
+            licenses,
+            [
+                "Apache-2.0",
+                "GPL-2",
+                "GPL-3",
+                "LGPL-2.1",
+                "MIT",
+                "public-domain"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = "MIT || ( GPL-2+ BSD ) doc? ( FDL-1.3 ) ( Apache-2.0 )";
+
+        assert_eq!(input.parse::<LicenseSpec>().unwrap().to_string(), input);
+
+        let input = "-* @FREE -MIT *";
+
+        assert_eq!(input.parse::<AcceptLicense>().unwrap().to_string(), input);
+    }
+}
@@ -0,0 +1,138 @@
+use nom::{
+    branch::alt,
+    bytes::{
+        complete::{tag, take_till},
+        take_while,
+    },
+    character::complete::{line_ending, space0, space1},
+    combinator::{opt, recognize},
+    multi::{many1, separated_list0, separated_list1},
+    sequence::{delimited, preceded, terminated},
+    Parser,
+};
+
+use crate::{
+    depend::parsers::{conditional_group, group, spec},
+    error::Expected,
+    parser_utils::{context, take_1_if, whitespace},
+    ParseResult,
+};
+
+use super::{AcceptLicense, Expr, License, LicenseGroups, LicenseSpec, Pattern, Token};
+
+pub fn license(input: &str) -> ParseResult<'_, License> {
+    context(Expected::License, name)
+        .map(|name: &str| License(name.to_string()))
+        .parse_complete(input)
+}
+
+pub fn exprs(input: &str) -> ParseResult<'_, Vec<Expr>> {
+    separated_list1(whitespace, expr).parse(input)
+}
+
+pub fn license_spec(input: &str) -> ParseResult<'_, LicenseSpec> {
+    spec(exprs).map(LicenseSpec).parse_complete(input)
+}
+
+pub fn expr(input: &str) -> ParseResult<'_, Expr> {
+    let conditional =
+        conditional_group(expr).map(|(conditional, exprs)| Expr::Conditional(conditional, exprs));
+
+    let any_of = preceded((tag("||"), whitespace), group(expr)).map(Expr::AnyOf);
+
+    let all_of = group(expr).map(Expr::AllOf);
+
+    let license = license.map(Expr::License);
+
+    context(Expected::Expr, alt((conditional, any_of, all_of, license))).parse_complete(input)
+}
+
+// ACCEPT_LICENSE, a list of licenses, @groups and *, any of which may be
+// negated with a leading -
+pub fn accept_license(input: &str) -> ParseResult<'_, AcceptLicense> {
+    let pattern = alt((
+        tag("*").map(|_| Pattern::All),
+        preceded(tag("@"), name).map(|name: &str| Pattern::Group(name.to_string())),
+        license.map(Pattern::License),
+    ));
+
+    let token = (opt(tag("-")), pattern).map(|(minus, pattern)| match minus {
+        Some(_) => Token::Reject(pattern),
+        None => Token::Accept(pattern),
+    });
+
+    spec(separated_list1(whitespace, token))
+        .map(AcceptLicense)
+        .parse_complete(input)
+}
+
+// profiles/license_groups, one group per line followed by its members,
+// which are licenses or other @groups
+pub fn license_groups(input: &str) -> ParseResult<'_, LicenseGroups> {
+    let member = recognize((opt(tag("@")), name)).map(str::to_string);
+
+    let group = (name, many1(preceded(space1, member)))
+        .map(|(name, members)| Some((name.to_string(), members)));
+
+    let comment = (tag("#"), take_till(|c| c == '\n')).map(|_| None);
+
+    let line = delimited(space0, opt(alt((comment, group))), space0).map(Option::flatten);
+
+    terminated(separated_list0(line_ending, line), opt(whitespace))
+        .map(|lines| LicenseGroups(lines.into_iter().flatten().collect()))
+        .parse_complete(input)
+}
+
+fn name(input: &str) -> ParseResult<'_, &str> {
+    recognize((
+        take_1_if(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+        take_while(|c: char| c.is_ascii_alphanumeric() || matches!(c, '+' | '_' | '.' | '-')),
+    ))
+    .parse_complete(input)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_license() {
+        for input in ["GPL-2+", "Apache-2.0", "LGPL-2.1", "BSD_3", "public-domain"] {
+            assert_eq!(license(input).unwrap(), ("", License(input.to_string())));
+        }
+
+        for input in ["-GPL-2", ".foo", "+bar", "||"] {
+            assert!(license(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_license_spec() {
+        let (rest, spec) =
+            license_spec("MIT || ( GPL-2 BSD ) doc? ( FDL-1.3 ) ( Apache-2.0 )").unwrap();
+
+        assert!(rest.is_empty());
+
+        assert!(matches!(
+            spec.0.as_slice(),
+            [
+                Expr::License(_),
+                Expr::AnyOf(_),
+                Expr::Conditional(_, _),
+                Expr::AllOf(_)
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_license_groups() {
+        let input = "# a comment\n\nGPL-COMPATIBLE Apache-2.0 GPL-2+\nFREE @GPL-COMPATIBLE MIT  \n";
+
+        let (rest, groups) = license_groups(input).unwrap();
+
+        assert!(rest.is_empty());
+
+        assert_eq!(groups.0["FREE"], ["@GPL-COMPATIBLE", "MIT"]);
+    }
+}
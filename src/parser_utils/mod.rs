@@ -2,12 +2,17 @@ use core::{clone::Clone, fmt};
 
 use nom::{
     bytes::{complete::take_while1, take, take_while_m_n},
-    combinator::{complete, peek},
+    combinator::{complete, eof, peek},
     error::ParseError,
     Input, Mode, Parser,
 };
 
-use crate::ParseResult;
+use crate::{
+    error::{Error, Expected},
+    ParseResult,
+};
+
+pub struct Context<F>(Expected, F);
 
 pub struct Debug<F>(F);
 
@@ -17,6 +22,29 @@ pub struct Search<F>(F);
 
 pub struct Uncut<F>(F);
 
+impl<'a, F> Parser<&'a str> for Context<F>
+where
+    F: Parser<&'a str, Error = crate::error::ParseError<'a>>,
+{
+    type Output = F::Output;
+    type Error = F::Error;
+
+    fn process<OM: nom::OutputMode>(
+        &mut self,
+        input: &'a str,
+    ) -> nom::PResult<OM, &'a str, Self::Output, Self::Error> {
+        let expected = self.0;
+
+        match self.1.process::<OM>(input) {
+            Err(nom::Err::Error(e)) => Err(nom::Err::Error(OM::Error::map(e, |e| {
+                e.push(input, expected)
+            }))),
+            Err(nom::Err::Failure(e)) => Err(nom::Err::Failure(e.push(input, expected))),
+            result => result,
+        }
+    }
+}
+
 impl<I, O, E, F> Parser<I> for Debug<F>
 where
     I: Input + fmt::Debug,
@@ -59,7 +87,6 @@ where
         input: I,
     ) -> nom::PResult<OM, I, Self::Output, Self::Error> {
         let mut remaining = input.clone();
-        let mut consumed = 0usize;
 
         while self.0.parse(remaining.clone()).is_err() {
             remaining = match take(1usize).parse(remaining.clone()) {
@@ -68,10 +95,12 @@ where
                 Err(nom::Err::Failure(e)) => return Err(nom::Err::Failure(e)),
                 Err(nom::Err::Incomplete(i)) => return Err(nom::Err::Incomplete(i)),
             };
-
-            consumed += 1;
         }
 
+        // take(1) steps over a whole character, which may be several bytes,
+        // while input.take counts in the input's own units
+        let consumed = input.input_len() - remaining.input_len();
+
         Ok((remaining, OM::Output::bind(|| input.take(consumed))))
     }
 }
@@ -97,6 +126,26 @@ where
     }
 }
 
+// records what was being parsed when an error occurs, so it can be reported
+// back to the user
+pub fn context<'a, F>(expected: Expected, parser: F) -> Context<F>
+where
+    F: Parser<&'a str, Error = crate::error::ParseError<'a>>,
+{
+    Context(expected, parser)
+}
+
+// runs the parser over the entire input, used to implement `FromStr`
+pub(crate) fn parse_all<'a, O, F>(input: &'a str, parser: F) -> Result<O, Error>
+where
+    F: Parser<&'a str, Output = O, Error = crate::error::ParseError<'a>>,
+{
+    match (parser, context(Expected::Eof, eof)).parse_complete(input) {
+        Ok((_, (output, _))) => Ok(output),
+        Err(e) => Err(Error::new(input, e)),
+    }
+}
+
 // not sure why we cant return impl Parser here
 pub fn debug<I, O, E, F>(parser: F) -> Debug<F>
 where
@@ -125,7 +174,7 @@ where
     take_while_m_n(1, 1, f)
 }
 
-pub fn whitespace(input: &str) -> ParseResult<&str> {
+pub fn whitespace(input: &str) -> ParseResult<'_, &str> {
     take_while1(|c: char| c.is_ascii_whitespace()).parse_complete(input)
 }
 
@@ -0,0 +1,450 @@
+pub mod parsers;
+
+use core::{
+    fmt::{self, Display},
+    iter::ExactSizeIterator,
+    str::FromStr,
+};
+use std::collections::{BTreeSet, HashSet};
+
+use crate::{
+    depend::{Conditional, Expr, UseRequirement},
+    error::Error,
+    parser_utils::parse_all,
+    useflag::UseFlag,
+};
+
+// the most candidates `RequiredUse::solve` keeps after each step
+const BEAM_WIDTH: usize = 16;
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequiredUse(Vec<Expr>);
+
+// a clause of REQUIRED_USE that is not satisfied, along with the conditionals
+// that made it apply, outermost first
+#[derive(Clone, Debug)]
+pub struct Violation<'a> {
+    conditionals: Vec<&'a Conditional>,
+    expr: &'a Expr,
+}
+
+// the flags that have to be toggled to satisfy REQUIRED_USE
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    enable: Vec<UseFlag>,
+    disable: Vec<UseFlag>,
+}
+
+impl RequiredUse {
+    pub fn exprs(&self) -> impl ExactSizeIterator<Item = &Expr> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn check(&self, useflags: &HashSet<UseFlag>) -> Result<(), Vec<Violation<'_>>> {
+        let mut violations = Vec::new();
+
+        check(&self.0, useflags, &mut Vec::new(), &mut violations);
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    // searches breadth first over the flags mentioned by the first clause
+    // that fails, as any fix has to toggle at least one of them. each flag is
+    // toggled at most once and only the BEAM_WIDTH candidates with the fewest
+    // failing clauses are kept after each step, ties broken by flag name. as
+    // long as nothing had to be dropped the first configuration that passes
+    // toggles as few flags as possible, otherwise it is merely a small set
+    pub fn solve(&self, useflags: &HashSet<UseFlag>) -> Option<Changes> {
+        let mut frontier = vec![BTreeSet::new()];
+        let mut seen = HashSet::new();
+
+        while !frontier.is_empty() {
+            let mut next = Vec::new();
+
+            for toggled in frontier {
+                let mut candidate = toggle(useflags, &toggled);
+
+                let Err(violations) = self.check(&candidate) else {
+                    let (enable, disable) = toggled
+                        .into_iter()
+                        .cloned()
+                        .partition(|flag| candidate.contains(flag));
+
+                    return Some(Changes { enable, disable });
+                };
+
+                let mut flags = violations[0]
+                    .conditionals
+                    .iter()
+                    .map(|conditional| conditional.useflag())
+                    .collect::<BTreeSet<_>>();
+
+                referenced_flags(violations[0].expr, &mut flags);
+
+                for &flag in flags.difference(&toggled) {
+                    let mut toggled = toggled.clone();
+
+                    toggled.insert(flag);
+
+                    if !seen.insert(toggled.clone()) {
+                        continue;
+                    }
+
+                    let enabled = candidate.remove(flag);
+
+                    if !enabled {
+                        candidate.insert(flag.clone());
+                    }
+
+                    let failing = self
+                        .check(&candidate)
+                        .map_or_else(|violations| violations.len(), |()| 0);
+
+                    if enabled {
+                        candidate.insert(flag.clone());
+                    } else {
+                        candidate.remove(flag);
+                    }
+
+                    next.push((failing, toggled));
+                }
+            }
+
+            next.sort_by_key(|(failing, _)| *failing);
+
+            frontier = next
+                .into_iter()
+                .take(BEAM_WIDTH)
+                .map(|(_, toggled)| toggled)
+                .collect();
+        }
+
+        None
+    }
+}
+
+impl<'a> Violation<'a> {
+    pub fn conditionals(&self) -> &[&'a Conditional] {
+        &self.conditionals
+    }
+
+    pub fn expr(&self) -> &'a Expr {
+        self.expr
+    }
+}
+
+impl Changes {
+    pub fn enable(&self) -> &[UseFlag] {
+        &self.enable
+    }
+
+    pub fn disable(&self) -> &[UseFlag] {
+        &self.disable
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.enable.is_empty() && self.disable.is_empty()
+    }
+
+    pub fn apply(&self, useflags: &HashSet<UseFlag>) -> HashSet<UseFlag> {
+        let mut useflags = useflags.clone();
+
+        for flag in &self.disable {
+            useflags.remove(flag);
+        }
+
+        useflags.extend(self.enable.iter().cloned());
+
+        useflags
+    }
+}
+
+impl From<RequiredUse> for Vec<Expr> {
+    fn from(required_use: RequiredUse) -> Self {
+        required_use.0
+    }
+}
+
+impl FromStr for RequiredUse {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::required_use)
+    }
+}
+
+impl Display for RequiredUse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, expr) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", expr)?;
+        }
+
+        Ok(())
+    }
+}
+
+// displayed as the failing clause nested in its conditionals, so that it
+// reads like the part of REQUIRED_USE it came from
+impl Display for Violation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for conditional in &self.conditionals {
+            write!(f, "{} ( ", conditional)?;
+        }
+
+        write!(f, "{}", self.expr)?;
+
+        for _ in &self.conditionals {
+            write!(f, " )")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Changes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let enable = self.enable.iter().map(|flag| flag.to_string());
+        let disable = self.disable.iter().map(|flag| format!("-{}", flag));
+
+        for (i, flag) in enable.chain(disable).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", flag)?;
+        }
+
+        Ok(())
+    }
+}
+
+// descends into conditionals that apply and all-of groups so that the
+// reported clause is as specific as possible
+fn check<'a>(
+    exprs: &'a [Expr],
+    useflags: &HashSet<UseFlag>,
+    conditionals: &mut Vec<&'a Conditional>,
+    violations: &mut Vec<Violation<'a>>,
+) {
+    for expr in exprs {
+        match expr {
+            Expr::Condtional(conditional, exprs) => {
+                if conditional.enabled(useflags) {
+                    conditionals.push(conditional);
+                    check(exprs, useflags, conditionals, violations);
+                    conditionals.pop();
+                }
+            }
+            Expr::AllOf(exprs) => check(exprs, useflags, conditionals, violations),
+            _ => {
+                if satisfied(expr, useflags) == Some(false) {
+                    violations.push(Violation {
+                        conditionals: conditionals.clone(),
+                        expr,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn toggle(useflags: &HashSet<UseFlag>, toggled: &BTreeSet<&UseFlag>) -> HashSet<UseFlag> {
+    let mut useflags = useflags.clone();
+
+    for &flag in toggled {
+        if !useflags.remove(flag) {
+            useflags.insert(flag.clone());
+        }
+    }
+
+    useflags
+}
+
+// returns None for conditionals that do not apply, these are left out when
+// counting the members of a group, and groups left empty are satisfied
+fn satisfied(expr: &Expr, useflags: &HashSet<UseFlag>) -> Option<bool> {
+    let members = |exprs: &[Expr]| {
+        exprs
+            .iter()
+            .filter_map(|expr| satisfied(expr, useflags))
+            .collect::<Vec<_>>()
+    };
+
+    match expr {
+        Expr::Atom(_) => Some(true),
+        Expr::UseRequirement(UseRequirement::Positive(flag)) => Some(useflags.contains(flag)),
+        Expr::UseRequirement(UseRequirement::Negative(flag)) => Some(!useflags.contains(flag)),
+        Expr::Condtional(conditional, exprs) => {
+            if conditional.enabled(useflags) {
+                Some(members(exprs).into_iter().all(|satisfied| satisfied))
+            } else {
+                None
+            }
+        }
+        Expr::AllOf(exprs) => Some(members(exprs).into_iter().all(|satisfied| satisfied)),
+        Expr::AnyOf(exprs) => {
+            let members = members(exprs);
+
+            Some(members.is_empty() || members.contains(&true))
+        }
+        Expr::OneOf(exprs) => {
+            let members = members(exprs);
+
+            Some(members.is_empty() || members.iter().filter(|&&satisfied| satisfied).count() == 1)
+        }
+        Expr::AtMostOneOf(exprs) => Some(
+            members(exprs)
+                .into_iter()
+                .filter(|&satisfied| satisfied)
+                .count()
+                <= 1,
+        ),
+    }
+}
+
+fn referenced_flags<'a>(expr: &'a Expr, flags: &mut BTreeSet<&'a UseFlag>) {
+    match expr {
+        Expr::Atom(_) => (),
+        Expr::UseRequirement(UseRequirement::Positive(flag) | UseRequirement::Negative(flag)) => {
+            flags.insert(flag);
+        }
+        Expr::Condtional(conditional, exprs) => {
+            flags.insert(conditional.useflag());
+
+            for expr in exprs {
+                referenced_flags(expr, flags);
+            }
+        }
+        Expr::AllOf(exprs) | Expr::AnyOf(exprs) | Expr::OneOf(exprs) | Expr::AtMostOneOf(exprs) => {
+            for expr in exprs {
+                referenced_flags(expr, flags);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test_utils::useflags;
+
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let required_use = "gui? ( || ( qt5 qt6 ) ) ^^ ( a b ) ?? ( c d ) !e? ( f ) ( g? ( !h ) )"
+            .parse::<RequiredUse>()
+            .unwrap();
+
+        let table = [
+            (&["a", "e"][..], &[][..]),
+            (&["a", "e", "g"][..], &[][..]),
+            (&["gui", "b", "e"][..], &["gui? ( || ( qt5 qt6 ) )"][..]),
+            (
+                &["a", "b", "c", "d", "e"][..],
+                &["^^ ( a b )", "?? ( c d )"][..],
+            ),
+            (&[][..], &["^^ ( a b )", "!e? ( f )"][..]),
+            (&["a", "e", "g", "h"][..], &["g? ( !h )"][..]),
+        ];
+
+        for (flags, expected) in table {
+            let violations = match required_use.check(&useflags(flags)) {
+                Ok(()) => Vec::new(),
+                Err(violations) => violations.iter().map(|v| v.to_string()).collect(),
+            };
+
+            assert_eq!(violations, expected, "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn test_empty_groups() {
+        let required_use = "|| ( foo? ( a ) ) ^^ ( bar? ( b ) )"
+            .parse::<RequiredUse>()
+            .unwrap();
+
+        assert!(required_use.check(&useflags(&[])).is_ok());
+
+        assert!(required_use.check(&useflags(&["foo"])).is_err());
+    }
+
+    #[test]
+    fn test_solve() {
+        let table = [
+            ("^^ ( a b )", &["a"][..], Some("")),
+            ("^^ ( a b )", &[][..], Some("a")),
+            ("^^ ( a b c )", &["a", "b", "c"][..], Some("-a -b")),
+            ("gui? ( || ( qt5 qt6 ) )", &["gui"][..], Some("-gui")),
+            ("|| ( qt5 qt6 )", &["gui"][..], Some("qt5")),
+            ("?? ( a b ) a? ( b )", &["a"][..], Some("-a")),
+            ("a !a", &[][..], None),
+        ];
+
+        for (input, flags, expected) in table {
+            let required_use = input.parse::<RequiredUse>().unwrap();
+
+            let changes = required_use.solve(&useflags(flags));
+
+            assert_eq!(
+                changes
+                    .as_ref()
+                    .map(|changes| changes.to_string())
+                    .as_deref(),
+                expected,
+                "{input} with {flags:?}"
+            );
+
+            if let Some(changes) = changes {
+                assert!(required_use.check(&changes.apply(&useflags(flags))).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve_many_flags() {
+        let flags = (0..40).map(|i| format!("f{i}")).collect::<Vec<_>>();
+        let enabled = useflags(&flags.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let independent = flags
+            .iter()
+            .map(|flag| format!("{flag}? ( !{flag} )"))
+            .collect::<Vec<_>>()
+            .join(" ")
+            .parse::<RequiredUse>()
+            .unwrap();
+
+        let changes = independent.solve(&enabled).unwrap();
+
+        assert_eq!(changes.disable().len(), 40);
+
+        assert!(independent.check(&changes.apply(&enabled)).is_ok());
+
+        let at_most_one = format!("?? ( {} )", flags.join(" "))
+            .parse::<RequiredUse>()
+            .unwrap();
+
+        let changes = at_most_one.solve(&enabled).unwrap();
+
+        assert_eq!(changes.disable().len(), 39);
+
+        let unsatisfiable = format!("?? ( {} ) a !a", flags.join(" "))
+            .parse::<RequiredUse>()
+            .unwrap();
+
+        assert_eq!(unsatisfiable.solve(&enabled), None);
+    }
+}
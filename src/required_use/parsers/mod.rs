@@ -0,0 +1,47 @@
+use nom::{combinator::verify, Parser};
+
+use crate::{
+    depend::{parsers::dep_spec, Expr},
+    ParseResult,
+};
+
+use super::RequiredUse;
+
+// REQUIRED_USE shares the dependency grammar, but only ever constrains USE
+// flags, so atoms anywhere in the specification are rejected
+pub fn required_use(input: &str) -> ParseResult<'_, RequiredUse> {
+    verify(dep_spec, |spec| {
+        spec.exprs().all(|expr| !contains_atom(expr))
+    })
+    .map(|spec| RequiredUse(spec.into()))
+    .parse_complete(input)
+}
+
+fn contains_atom(expr: &Expr) -> bool {
+    match expr {
+        Expr::Atom(_) => true,
+        Expr::UseRequirement(_) => false,
+        Expr::AllOf(exprs)
+        | Expr::AnyOf(exprs)
+        | Expr::OneOf(exprs)
+        | Expr::AtMostOneOf(exprs)
+        | Expr::Condtional(_, exprs) => exprs.iter().any(contains_atom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_required_use() {
+        let (_, spec) = required_use("gui? ( || ( qt5 qt6 ) ) ?? ( a b )").unwrap();
+
+        assert_eq!(spec.exprs().len(), 2);
+
+        assert!(required_use("").unwrap().1.is_empty());
+
+        assert!(required_use("foo? ( cat/pkg )").is_err());
+    }
+}
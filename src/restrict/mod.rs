@@ -0,0 +1,300 @@
+pub mod parsers;
+
+use core::{
+    fmt::{self, Display},
+    iter::ExactSizeIterator,
+    str::FromStr,
+    write,
+};
+use std::collections::HashSet;
+
+use crate::{
+    depend::{write_group, Conditional},
+    error::Error,
+    parser_utils::parse_all,
+    useflag::UseFlag,
+};
+
+// tokens understood by package managers, anything else is kept as unknown
+// since overlays and newer tools add their own
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Restrict {
+    Fetch,
+    Mirror,
+    Test,
+    Strip,
+    Userpriv,
+    Bindist,
+    Binchecks,
+    Installsources,
+    Splitdebug,
+    Primaryuri,
+    NetworkSandbox,
+    PreserveLibs,
+    Unknown(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Property {
+    Interactive,
+    Live,
+    TestNetwork,
+    TestPrivileged,
+    Unknown(String),
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expr<T> {
+    Token(T),
+    AllOf(Vec<Expr<T>>),
+    Conditional(Conditional, Vec<Expr<T>>),
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenSpec<T>(Vec<Expr<T>>);
+
+pub type RestrictSpec = TokenSpec<Restrict>;
+
+pub type PropertiesSpec = TokenSpec<Property>;
+
+impl<T> TokenSpec<T> {
+    pub fn exprs(&self) -> impl ExactSizeIterator<Item = &Expr<T>> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // every token whose conditionals are enabled, in order
+    pub fn evaluate(&self, useflags: &HashSet<UseFlag>) -> Vec<&T> {
+        let mut tokens = Vec::new();
+
+        evaluate(&self.0, useflags, &mut tokens);
+
+        tokens
+    }
+
+    pub fn contains(&self, useflags: &HashSet<UseFlag>, token: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.evaluate(useflags).contains(&token)
+    }
+}
+
+impl<T> Default for TokenSpec<T> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<T> From<Vec<Expr<T>>> for TokenSpec<T> {
+    fn from(exprs: Vec<Expr<T>>) -> Self {
+        Self(exprs)
+    }
+}
+
+impl From<&str> for Restrict {
+    fn from(s: &str) -> Self {
+        match s {
+            "fetch" => Self::Fetch,
+            "mirror" => Self::Mirror,
+            "test" => Self::Test,
+            "strip" => Self::Strip,
+            "userpriv" => Self::Userpriv,
+            "bindist" => Self::Bindist,
+            "binchecks" => Self::Binchecks,
+            "installsources" => Self::Installsources,
+            "splitdebug" => Self::Splitdebug,
+            "primaryuri" => Self::Primaryuri,
+            "network-sandbox" => Self::NetworkSandbox,
+            "preserve-libs" => Self::PreserveLibs,
+            _ => Self::Unknown(s.to_string()),
+        }
+    }
+}
+
+impl From<&str> for Property {
+    fn from(s: &str) -> Self {
+        match s {
+            "interactive" => Self::Interactive,
+            "live" => Self::Live,
+            "test_network" => Self::TestNetwork,
+            "test_privileged" => Self::TestPrivileged,
+            _ => Self::Unknown(s.to_string()),
+        }
+    }
+}
+
+impl FromStr for Restrict {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::token).map(Self::from)
+    }
+}
+
+impl FromStr for Property {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::token).map(Self::from)
+    }
+}
+
+impl<T> FromStr for TokenSpec<T>
+where
+    T: for<'a> From<&'a str>,
+{
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::token_spec)
+    }
+}
+
+impl Display for Restrict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::Fetch => "fetch",
+            Self::Mirror => "mirror",
+            Self::Test => "test",
+            Self::Strip => "strip",
+            Self::Userpriv => "userpriv",
+            Self::Bindist => "bindist",
+            Self::Binchecks => "binchecks",
+            Self::Installsources => "installsources",
+            Self::Splitdebug => "splitdebug",
+            Self::Primaryuri => "primaryuri",
+            Self::NetworkSandbox => "network-sandbox",
+            Self::PreserveLibs => "preserve-libs",
+            Self::Unknown(s) => s.as_str(),
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::Interactive => "interactive",
+            Self::Live => "live",
+            Self::TestNetwork => "test_network",
+            Self::TestPrivileged => "test_privileged",
+            Self::Unknown(s) => s.as_str(),
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl<T: Display> Display for Expr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Token(token) => write!(f, "{}", token),
+            Self::AllOf(exprs) => write_group(exprs, None, f, |expr, _, f| write!(f, "{}", expr)),
+            Self::Conditional(conditional, exprs) => {
+                write!(f, "{} ", conditional)?;
+                write_group(exprs, None, f, |expr, _, f| write!(f, "{}", expr))
+            }
+        }
+    }
+}
+
+impl<T: Display> Display for TokenSpec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, expr) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", expr)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn evaluate<'a, T>(exprs: &'a [Expr<T>], useflags: &HashSet<UseFlag>, out: &mut Vec<&'a T>) {
+    for expr in exprs {
+        match expr {
+            Expr::Token(token) => out.push(token),
+            Expr::AllOf(exprs) => evaluate(exprs, useflags, out),
+            Expr::Conditional(conditional, exprs) => {
+                if conditional.enabled(useflags) {
+                    evaluate(exprs, useflags, out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test_utils::useflags;
+
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let spec = "mirror !test? ( test ) bindist? ( fetch ) my-token"
+            .parse::<RestrictSpec>()
+            .unwrap();
+
+        let table = [
+            (
+                &[][..],
+                &[
+                    Restrict::Mirror,
+                    Restrict::Test,
+                    Restrict::Unknown("my-token".to_string()),
+                ][..],
+            ),
+            (
+                &["test", "bindist"][..],
+                &[
+                    Restrict::Mirror,
+                    Restrict::Fetch,
+                    Restrict::Unknown("my-token".to_string()),
+                ][..],
+            ),
+        ];
+
+        for (flags, expected) in table {
+            let tokens = spec.evaluate(&useflags(flags));
+
+            assert_eq!(tokens, expected.iter().collect::<Vec<_>>(), "{flags:?}");
+        }
+
+        assert!(spec.contains(&useflags(&[]), &Restrict::Test));
+
+        assert!(!spec.contains(&useflags(&["test"]), &Restrict::Test));
+    }
+
+    #[test]
+    fn test_properties() {
+        let spec = "live test? ( test_network ) foo"
+            .parse::<PropertiesSpec>()
+            .unwrap();
+
+        assert!(spec.contains(&useflags(&[]), &Property::Live));
+
+        assert!(spec.contains(&useflags(&["test"]), &Property::TestNetwork));
+
+        assert!(spec.contains(&useflags(&[]), &"foo".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = "fetch network-sandbox test? ( userpriv ) ( !bindist? ( custom ) )";
+
+        assert_eq!(input.parse::<RestrictSpec>().unwrap().to_string(), input);
+    }
+}
@@ -0,0 +1,74 @@
+use nom::{branch::alt, multi::separated_list1, Parser};
+
+use crate::{
+    depend::parsers::{self, conditional_group, group, spec},
+    error::Expected,
+    parser_utils::{context, whitespace},
+    ParseResult,
+};
+
+use super::{Expr, TokenSpec};
+
+pub fn exprs<T>(input: &str) -> ParseResult<'_, Vec<Expr<T>>>
+where
+    T: for<'a> From<&'a str>,
+{
+    separated_list1(whitespace, expr).parse(input)
+}
+
+pub fn token_spec<T>(input: &str) -> ParseResult<'_, TokenSpec<T>>
+where
+    T: for<'a> From<&'a str>,
+{
+    spec(exprs).map(TokenSpec).parse_complete(input)
+}
+
+pub fn expr<T>(input: &str) -> ParseResult<'_, Expr<T>>
+where
+    T: for<'a> From<&'a str>,
+{
+    let conditional =
+        conditional_group(expr).map(|(conditional, exprs)| Expr::Conditional(conditional, exprs));
+
+    let all_of = group(expr).map(Expr::AllOf);
+
+    let token = token.map(|token| Expr::Token(T::from(token)));
+
+    context(Expected::Expr, alt((conditional, all_of, token))).parse_complete(input)
+}
+
+// any word that could not be mistaken for a group or a conditional, unknown
+// tokens are kept rather than rejected
+pub fn token(input: &str) -> ParseResult<'_, &str> {
+    context(Expected::Token, parsers::token(&[])).parse_complete(input)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::restrict::{Property, Restrict};
+
+    use super::*;
+
+    #[test]
+    fn test_token_spec() {
+        let (rest, spec) = token_spec::<Restrict>("mirror test? ( userpriv ) ( foo )").unwrap();
+
+        assert!(rest.is_empty());
+
+        assert!(matches!(
+            spec.0.as_slice(),
+            [
+                Expr::Token(Restrict::Mirror),
+                Expr::Conditional(_, _),
+                Expr::AllOf(_)
+            ]
+        ));
+
+        let (_, spec) = token_spec::<Property>(" live\n").unwrap();
+
+        assert!(matches!(spec.0.as_slice(), [Expr::Token(Property::Live)]));
+
+        assert!(token_spec::<Restrict>("test? ( )").is_err());
+    }
+}
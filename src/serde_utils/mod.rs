@@ -0,0 +1,339 @@
+// The serde impls derived on the types in this crate serialize them as
+// structured objects, except for the atom, USE flag and license types whose
+// invariants are only enforced by their parsers. Those always serialize as
+// their string form and deserialize through `FromStr`, so invalid values such
+// as a version without any numbers can not be constructed.
+//
+// The items in this module serialize anything else that implements `Display`
+// and `FromStr` as its canonical string form too, for example a `DepSpec` as
+// `"foo? ( cat/pkg )"` rather than a tree of expressions, while `structured`
+// goes the other way and writes the atom types out as objects of their
+// components, still validating them when they are read back.
+//
+// #[derive(Serialize, Deserialize)]
+// struct Report {
+//     #[serde(with = "gentoo_utils::serde_utils::string")]
+//     atom: Atom,
+//     installed: Vec<AsString<Cpv>>,
+// }
+
+use core::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    atom::{Atom, Category, Cpv, Name, Repo, Slot, Version},
+    error::Error,
+    license::License,
+    useflag::{UseDep, UseFlag},
+};
+
+pub mod structured;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AsString<T>(pub T);
+
+pub mod string {
+    use core::{fmt::Display, str::FromStr};
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Display,
+        S: Serializer,
+    {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl<T: Display> Serialize for AsString<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        string::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for AsString<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        string::deserialize(deserializer).map(AsString)
+    }
+}
+
+// the conversions used by `#[serde(try_from = "String", into = "String")]`
+macro_rules! string_conversions {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl TryFrom<String> for $ty {
+                type Error = Error;
+
+                fn try_from(s: String) -> Result<Self, Self::Error> {
+                    s.parse()
+                }
+            }
+
+            impl From<$ty> for String {
+                fn from(value: $ty) -> Self {
+                    value.to_string()
+                }
+            }
+        )*
+    };
+}
+
+string_conversions!(Category, Name, Repo, Slot, Version, Cpv, Atom, UseFlag, UseDep, License);
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        atom::{Atom, Cpv, Slot, Version},
+        depend::{DepSpec, Expr},
+        useflag::UseDep,
+        vdb::{Content, Dir, Obj, Sym},
+    };
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Report {
+        #[serde(with = "string")]
+        atom: Atom,
+        #[serde(with = "string")]
+        version: Version,
+        #[serde(with = "string")]
+        slot: Slot,
+        usedeps: Vec<AsString<UseDep>>,
+        expr: AsString<Expr>,
+        content: AsString<Content>,
+    }
+
+    #[test]
+    fn test_string_round_trip() {
+        let report = Report {
+            atom: "!!>=dev-lang/rust-1.80.1-r1:stable/1.80=::gentoo[llvm(+),-wasm?]"
+                .parse()
+                .unwrap(),
+            version: "1.0_alpha1_p20250326-r1".parse().unwrap(),
+            slot: "0/3=".parse().unwrap(),
+            usedeps: vec![AsString("!foo=".parse().unwrap())],
+            expr: AsString("foo? ( || ( cat/a cat/b ) )".parse().unwrap()),
+            content: AsString(
+                "sym /usr/bin/cargo -> cargo-1.80 1739589188"
+                    .parse()
+                    .unwrap(),
+            ),
+        };
+
+        let json = serde_json::to_string(&report).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"atom":"!!>=dev-lang/rust-1.80.1-r1:stable/1.80=::gentoo[llvm(+),-wasm?]","version":"1.0_alpha1_p20250326-r1","slot":"0/3=","usedeps":["!foo="],"expr":"foo? ( || ( cat/a cat/b ) )","content":"sym /usr/bin/cargo -> cargo-1.80 1739589188"}"#
+        );
+
+        let received: Report = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(received.atom.to_string(), report.atom.to_string());
+
+        assert_eq!(received.version.to_string(), report.version.to_string());
+
+        assert_eq!(received.slot, report.slot);
+
+        assert_eq!(received.usedeps[0].0.to_string(), "!foo=");
+
+        assert_eq!(received.expr, report.expr);
+
+        assert_eq!(received.content, report.content);
+    }
+
+    #[test]
+    fn test_string_with_invalid_input() {
+        assert!(serde_json::from_str::<AsString<Atom>>(r#"">=dev-lang/rust-1.0:""#).is_err());
+
+        assert!(serde_json::from_str::<AsString<Version>>("1").is_err());
+
+        assert!(serde_json::from_str::<AsString<Content>>(r#""obj /a 123 1""#).is_err());
+    }
+
+    #[test]
+    fn test_validated_types() {
+        let cpv: Cpv = "dev-lang/rust-1.80.1-r1".parse().unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&cpv).unwrap(),
+            r#""dev-lang/rust-1.80.1-r1""#
+        );
+
+        assert_eq!(
+            serde_json::from_str::<Cpv>(r#""dev-lang/rust-1.80.1-r1""#).unwrap(),
+            cpv
+        );
+
+        // these used to deserialize into values that panic when compared
+        assert!(serde_json::from_str::<Version>(
+            r#"{"numbers":[],"letter":null,"suffixes":[],"revision":null}"#
+        )
+        .is_err());
+
+        assert!(serde_json::from_str::<Version>(r#""""#).is_err());
+
+        assert!(serde_json::from_str::<Atom>(r#""dev-lang/rust-1.0::""#).is_err());
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Structured {
+        #[serde(with = "structured")]
+        atom: Atom,
+        #[serde(with = "structured")]
+        slot: Slot,
+        installed: Vec<structured::AsStructured<Cpv>>,
+    }
+
+    #[test]
+    fn test_structured_round_trip() {
+        let report = Structured {
+            atom: "!=dev-lang/python-3.12*:3.12::gentoo[sqlite,-tk(-)]"
+                .parse()
+                .unwrap(),
+            slot: "0/3=".parse().unwrap(),
+            installed: vec![structured::AsStructured(
+                "dev-lang/python-3.12.7_p1-r1".parse().unwrap(),
+            )],
+        };
+
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "atom": {
+                    "blocker": "Weak",
+                    "version_operator": "EqGlob",
+                    "category": "dev-lang",
+                    "name": "python",
+                    "version": {
+                        "numbers": ["3", "12"],
+                        "letter": null,
+                        "suffixes": [],
+                        "revision": null,
+                    },
+                    "slot": { "primary": "3.12", "sub": null, "operator": null },
+                    "repo": "gentoo",
+                    "usedeps": [
+                        { "negate": null, "useflag": "sqlite", "sign": null, "operator": null },
+                        { "negate": "Minus", "useflag": "tk", "sign": "Minus", "operator": null },
+                    ],
+                },
+                "slot": { "primary": "0", "sub": "3", "operator": "Eq" },
+                "installed": [{
+                    "category": "dev-lang",
+                    "name": "python",
+                    "version": {
+                        "numbers": ["3", "12", "7"],
+                        "letter": null,
+                        "suffixes": [{ "kind": "P", "number": "1" }],
+                        "revision": "1",
+                    },
+                }],
+            })
+        );
+
+        let received: Structured = serde_json::from_value(json).unwrap();
+
+        assert_eq!(received.atom, report.atom);
+
+        assert_eq!(received.slot, report.slot);
+
+        assert_eq!(received.installed, report.installed);
+    }
+
+    #[test]
+    fn test_structured_with_invalid_input() {
+        let version = |numbers: &[&str]| {
+            serde_json::json!({
+                "numbers": numbers,
+                "letter": null,
+                "suffixes": [],
+                "revision": null,
+            })
+        };
+
+        let parse = |value: serde_json::Value| {
+            structured::deserialize::<Version, _>(value).map_err(|e| e.to_string())
+        };
+
+        assert!(parse(version(&["1", "02"])).is_ok());
+
+        assert!(parse(version(&[])).is_err());
+
+        // spells out a valid version, but not with these components
+        assert!(parse(version(&["1.2"])).is_err());
+
+        let atom = serde_json::json!({
+            "blocker": null,
+            "version_operator": "EqGlob",
+            "category": "dev-lang",
+            "name": "rust",
+            "version": null,
+            "slot": null,
+            "repo": null,
+            "usedeps": [],
+        });
+
+        assert!(structured::deserialize::<Atom, _>(atom).is_err());
+    }
+
+    #[test]
+    fn test_derived_round_trip() {
+        let spec: DepSpec = "cat/pkg foo? ( || ( cat/a !bar? ( cat/b ) ) )"
+            .parse()
+            .unwrap();
+
+        let json = serde_json::to_string(&spec).unwrap();
+
+        let received: DepSpec = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(format!("{received:?}"), format!("{spec:?}"));
+
+        let contents = vec![
+            Content::Obj(Obj {
+                path: PathBuf::from("/usr/bin/rustc"),
+                md5: String::from("6c0d51586d94c272b160eb7ba6c61331"),
+                mtime: 1739589188.into(),
+            }),
+            Content::Dir(Dir {
+                path: PathBuf::from("/usr/bin"),
+            }),
+            Content::Sym(Sym {
+                src: PathBuf::from("/usr/bin/cargo"),
+                dest: PathBuf::from("cargo-1.80"),
+                mtime: 1739589188.into(),
+            }),
+        ];
+
+        let json = serde_json::to_string(&contents).unwrap();
+
+        let received: Vec<Content> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(received, contents);
+    }
+}
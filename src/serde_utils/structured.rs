@@ -0,0 +1,313 @@
+// Serializes the validated types as objects of their components instead of
+// their string form. The structs in this module mirror those types with public
+// fields, and deserializing goes through the parser by way of the string the
+// components spell out. A value is only accepted when parsing that string
+// gives back exactly the same components, so for example a version number of
+// "1.2" or a glob atom without a version is rejected.
+//
+// #[derive(Serialize, Deserialize)]
+// struct Report {
+//     #[serde(with = "gentoo_utils::serde_utils::structured")]
+//     atom: Atom,
+//     installed: Vec<AsStructured<Cpv>>,
+// }
+
+use core::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use serde::{de, de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    atom::{self, Blocker, Category, Name, Repo, SlotOperator, VersionOperator, VersionSuffixKind},
+    error::Error,
+    useflag::{self, Negate, Operator, Sign, UseFlag},
+};
+
+pub trait Structured: FromStr<Err = Error> {
+    type Repr: Serialize + DeserializeOwned + Display + PartialEq;
+
+    fn to_repr(&self) -> Self::Repr;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AsStructured<T>(pub T);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Slot {
+    pub primary: Option<String>,
+    pub sub: Option<String>,
+    pub operator: Option<SlotOperator>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionSuffix {
+    pub kind: VersionSuffixKind,
+    pub number: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Version {
+    pub numbers: Vec<String>,
+    pub letter: Option<char>,
+    pub suffixes: Vec<VersionSuffix>,
+    pub revision: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cpv {
+    pub category: Category,
+    pub name: Name,
+    pub version: Version,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UseDep {
+    pub negate: Option<Negate>,
+    pub useflag: UseFlag,
+    pub sign: Option<Sign>,
+    pub operator: Option<Operator>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Atom {
+    pub blocker: Option<Blocker>,
+    pub version_operator: Option<VersionOperator>,
+    pub category: Category,
+    pub name: Name,
+    pub version: Option<Version>,
+    pub slot: Option<Slot>,
+    pub repo: Option<Repo>,
+    pub usedeps: Vec<UseDep>,
+}
+
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Structured,
+    S: Serializer,
+{
+    value.to_repr().serialize(serializer)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Structured,
+    D: Deserializer<'de>,
+{
+    let repr = T::Repr::deserialize(deserializer)?;
+
+    let value = repr.to_string().parse::<T>().map_err(de::Error::custom)?;
+
+    if value.to_repr() != repr {
+        return Err(de::Error::custom(format!(
+            "{repr} does not parse back into the same components"
+        )));
+    }
+
+    Ok(value)
+}
+
+impl<T: Structured> Serialize for AsStructured<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, T: Structured> Deserialize<'de> for AsStructured<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(AsStructured)
+    }
+}
+
+impl Structured for atom::Slot {
+    type Repr = Slot;
+
+    fn to_repr(&self) -> Self::Repr {
+        Slot {
+            primary: self.primary().map(str::to_string),
+            sub: self.sub().map(str::to_string),
+            operator: self.operator().cloned(),
+        }
+    }
+}
+
+impl Structured for atom::Version {
+    type Repr = Version;
+
+    fn to_repr(&self) -> Self::Repr {
+        Version {
+            numbers: self
+                .numbers()
+                .map(|number| number.get().to_string())
+                .collect(),
+            letter: self.letter(),
+            suffixes: self
+                .suffixes()
+                .map(|suffix| VersionSuffix {
+                    kind: suffix.kind(),
+                    number: suffix.number().map(|number| number.get().to_string()),
+                })
+                .collect(),
+            revision: self.revision().map(|revision| revision.get().to_string()),
+        }
+    }
+}
+
+impl Structured for atom::Cpv {
+    type Repr = Cpv;
+
+    fn to_repr(&self) -> Self::Repr {
+        Cpv {
+            category: self.category().clone(),
+            name: self.name().clone(),
+            version: self.version().to_repr(),
+        }
+    }
+}
+
+impl Structured for useflag::UseDep {
+    type Repr = UseDep;
+
+    fn to_repr(&self) -> Self::Repr {
+        UseDep {
+            negate: self.negate(),
+            useflag: self.useflag().clone(),
+            sign: self.sign(),
+            operator: self.operator(),
+        }
+    }
+}
+
+impl Structured for atom::Atom {
+    type Repr = Atom;
+
+    fn to_repr(&self) -> Self::Repr {
+        Atom {
+            blocker: self.blocker(),
+            version_operator: self.version_operator(),
+            category: self.category().clone(),
+            name: self.name().clone(),
+            version: self.version().map(Structured::to_repr),
+            slot: self.slot().map(Structured::to_repr),
+            repo: self.repo().cloned(),
+            usedeps: self.usedeps().map(Structured::to_repr).collect(),
+        }
+    }
+}
+
+// the Display impls spell the components out the way the parsers expect them
+
+impl Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(primary) = self.primary.as_ref() {
+            write!(f, "{primary}")?;
+        }
+
+        if let Some(sub) = self.sub.as_ref() {
+            write!(f, "/{sub}")?;
+        }
+
+        if let Some(operator) = self.operator.as_ref() {
+            write!(f, "{operator}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.numbers.join("."))?;
+
+        if let Some(letter) = self.letter {
+            write!(f, "{letter}")?;
+        }
+
+        for suffix in &self.suffixes {
+            write!(f, "_{}", suffix.kind)?;
+
+            if let Some(number) = suffix.number.as_ref() {
+                write!(f, "{number}")?;
+            }
+        }
+
+        if let Some(revision) = self.revision.as_ref() {
+            write!(f, "-r{revision}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Cpv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}-{}", self.category, self.name, self.version)
+    }
+}
+
+impl Display for UseDep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(negate) = self.negate {
+            write!(f, "{negate}")?;
+        }
+
+        write!(f, "{}", self.useflag)?;
+
+        if let Some(sign) = self.sign {
+            write!(f, "{sign}")?;
+        }
+
+        if let Some(operator) = self.operator {
+            write!(f, "{operator}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(blocker) = self.blocker {
+            write!(f, "{blocker}")?;
+        }
+
+        if let Some(version_operator) = self.version_operator {
+            write!(f, "{version_operator}")?;
+        }
+
+        write!(f, "{}/{}", self.category, self.name)?;
+
+        if let Some(version) = self.version.as_ref() {
+            write!(f, "-{version}")?;
+        }
+
+        if let Some(VersionOperator::EqGlob) = self.version_operator {
+            write!(f, "*")?;
+        }
+
+        if let Some(slot) = self.slot.as_ref() {
+            write!(f, ":{slot}")?;
+        }
+
+        if let Some(repo) = self.repo.as_ref() {
+            write!(f, "::{repo}")?;
+        }
+
+        if !self.usedeps.is_empty() {
+            write!(f, "[")?;
+
+            for (i, usedep) in self.usedeps.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+
+                write!(f, "{usedep}")?;
+            }
+
+            write!(f, "]")?;
+        }
+
+        Ok(())
+    }
+}
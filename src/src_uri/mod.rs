@@ -0,0 +1,227 @@
+pub mod parsers;
+
+use core::{
+    fmt::{self, Display},
+    iter::ExactSizeIterator,
+    str::FromStr,
+    write,
+};
+use std::collections::HashSet;
+
+use crate::{
+    depend::{write_group, Conditional},
+    error::Error,
+    parser_utils::parse_all,
+    useflag::UseFlag,
+};
+
+// selective URI restrictions, lifting RESTRICT=fetch or RESTRICT=mirror for a
+// single URI
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Prefix {
+    Fetch,
+    Mirror,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Uri {
+    prefix: Option<Prefix>,
+    uri: String,
+    rename: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expr {
+    Uri(Uri),
+    AllOf(Vec<Expr>),
+    Conditional(Conditional, Vec<Expr>),
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SrcUri(Vec<Expr>);
+
+impl Uri {
+    pub fn prefix(&self) -> Option<Prefix> {
+        self.prefix
+    }
+
+    // the URI without its prefix
+    pub fn uri(&self) -> &str {
+        self.uri.as_str()
+    }
+
+    pub fn rename(&self) -> Option<&str> {
+        self.rename.as_deref()
+    }
+
+    // the name the file is saved as in DISTDIR
+    pub fn filename(&self) -> &str {
+        match &self.rename {
+            Some(rename) => rename.as_str(),
+            None => self.uri.rsplit('/').next().unwrap_or(&self.uri),
+        }
+    }
+}
+
+impl SrcUri {
+    pub fn exprs(&self) -> impl ExactSizeIterator<Item = &Expr> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // every URI whose conditionals are enabled
+    pub fn uris(&self, useflags: &HashSet<UseFlag>) -> Vec<&Uri> {
+        let mut uris = Vec::new();
+
+        collect_uris(&self.0, useflags, &mut uris);
+
+        uris
+    }
+
+    // the files fetched for a USE configuration, in order and without the
+    // duplicates that come from listing several mirrors for one file
+    pub fn distfiles(&self, useflags: &HashSet<UseFlag>) -> Vec<&str> {
+        let mut seen = HashSet::new();
+
+        self.uris(useflags)
+            .into_iter()
+            .map(Uri::filename)
+            .filter(|filename| seen.insert(*filename))
+            .collect()
+    }
+}
+
+impl From<Vec<Expr>> for SrcUri {
+    fn from(exprs: Vec<Expr>) -> Self {
+        Self(exprs)
+    }
+}
+
+impl From<SrcUri> for Vec<Expr> {
+    fn from(src_uri: SrcUri) -> Self {
+        src_uri.0
+    }
+}
+
+impl FromStr for Uri {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::uri)
+    }
+}
+
+impl FromStr for SrcUri {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, parsers::src_uri)
+    }
+}
+
+impl Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Fetch => write!(f, "fetch+"),
+            Self::Mirror => write!(f, "mirror+"),
+        }
+    }
+}
+
+impl Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(prefix) = self.prefix {
+            write!(f, "{}", prefix)?;
+        }
+
+        write!(f, "{}", self.uri)?;
+
+        if let Some(rename) = &self.rename {
+            write!(f, " -> {}", rename)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Uri(uri) => write!(f, "{}", uri),
+            Self::AllOf(exprs) => write_group(exprs, None, f, |expr, _, f| write!(f, "{}", expr)),
+            Self::Conditional(conditional, exprs) => {
+                write!(f, "{} ", conditional)?;
+                write_group(exprs, None, f, |expr, _, f| write!(f, "{}", expr))
+            }
+        }
+    }
+}
+
+impl Display for SrcUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, expr) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", expr)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn collect_uris<'a>(exprs: &'a [Expr], useflags: &HashSet<UseFlag>, out: &mut Vec<&'a Uri>) {
+    for expr in exprs {
+        match expr {
+            Expr::Uri(uri) => out.push(uri),
+            Expr::AllOf(exprs) => collect_uris(exprs, useflags, out),
+            Expr::Conditional(conditional, exprs) => {
+                if conditional.enabled(useflags) {
+                    collect_uris(exprs, useflags, out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test_utils::useflags;
+
+    use super::*;
+
+    #[test]
+    fn test_distfiles() {
+        let input = "https://github.com/foo/foo/archive/v1.0.tar.gz -> foo-1.0.tar.gz doc? ( mirror://gentoo/foo-docs-1.0.tar.xz https://example.org/foo-docs-1.0.tar.xz ) !doc? ( ( fetch+https://example.org/foo-man-1.0.tar.xz ) )";
+
+        let src_uri = input.parse::<SrcUri>().unwrap();
+
+        let table = [
+            (&[][..], &["foo-1.0.tar.gz", "foo-man-1.0.tar.xz"][..]),
+            (&["doc"][..], &["foo-1.0.tar.gz", "foo-docs-1.0.tar.xz"][..]),
+        ];
+
+        for (flags, expected) in table {
+            assert_eq!(src_uri.distfiles(&useflags(flags)), expected, "{flags:?}");
+        }
+
+        assert_eq!(src_uri.uris(&useflags(&["doc"])).len(), 3);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input =
+            "https://a/a.tar.gz -> a-1.tar.gz foo? ( mirror+https://b/b.tar.gz ) ( c.tar.gz )";
+
+        assert_eq!(input.parse::<SrcUri>().unwrap().to_string(), input);
+    }
+}
@@ -0,0 +1,179 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{cut, opt, value, verify},
+    multi::separated_list1,
+    sequence::preceded,
+    Parser,
+};
+
+use crate::{
+    depend::parsers::{conditional_group, group, spec, token},
+    eapi::Eapi,
+    error::Expected,
+    parser_utils::{context, whitespace},
+    ParseResult,
+};
+
+use super::{Expr, Prefix, SrcUri, Uri};
+
+pub fn exprs(input: &str) -> ParseResult<'_, Vec<Expr>> {
+    separated_list1(whitespace, expr).parse(input)
+}
+
+pub fn src_uri(input: &str) -> ParseResult<'_, SrcUri> {
+    spec(exprs).map(SrcUri).parse_complete(input)
+}
+
+pub fn expr(input: &str) -> ParseResult<'_, Expr> {
+    let conditional =
+        conditional_group(expr).map(|(conditional, exprs)| Expr::Conditional(conditional, exprs));
+
+    let all_of = group(expr).map(Expr::AllOf);
+
+    let uri = uri.map(Expr::Uri);
+
+    context(Expected::Expr, alt((conditional, all_of, uri))).parse_complete(input)
+}
+
+// renames (->) need EAPI 2, fetch+ and mirror+ prefixes need EAPI 8
+pub fn eapi_src_uri(eapi: Eapi) -> impl Fn(&str) -> ParseResult<'_, SrcUri> {
+    move |input| {
+        verify(src_uri, |src_uri: &SrcUri| {
+            src_uri.exprs().all(|expr| supported(eapi, expr))
+        })
+        .parse_complete(input)
+    }
+}
+
+// either a full URI or, for fetch restricted packages, a bare file name,
+// optionally followed by an arrow and the name to save it as
+pub fn uri(input: &str) -> ParseResult<'_, Uri> {
+    let prefix = alt((
+        value(Prefix::Fetch, tag("fetch+")),
+        value(Prefix::Mirror, tag("mirror+")),
+    ));
+
+    let rename = preceded((whitespace, tag("->"), whitespace), cut(filename));
+
+    let uri = verify(
+        (opt(prefix), word),
+        |(prefix, uri): &(Option<Prefix>, &str)| prefix.is_none() || uri.contains("://"),
+    );
+
+    context(
+        Expected::Uri,
+        (uri, opt(rename)).map(|((prefix, uri), rename)| Uri {
+            prefix,
+            uri: uri.to_string(),
+            rename: rename.map(str::to_string),
+        }),
+    )
+    .parse_complete(input)
+}
+
+// the rename arrow is not a word of its own
+fn word(input: &str) -> ParseResult<'_, &str> {
+    token(&["->"]).parse_complete(input)
+}
+
+fn filename(input: &str) -> ParseResult<'_, &str> {
+    verify(word, |token: &str| !token.contains('/')).parse_complete(input)
+}
+
+fn supported(eapi: Eapi, expr: &Expr) -> bool {
+    match expr {
+        Expr::Uri(uri) => {
+            (uri.rename.is_none() || eapi.has_src_uri_arrows())
+                && (uri.prefix.is_none() || eapi.has_selective_uri_restrictions())
+        }
+        Expr::AllOf(exprs) | Expr::Conditional(_, exprs) => {
+            exprs.iter().all(|expr| supported(eapi, expr))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_uri() {
+        let table = [
+            ("https://x/y.tar.gz", None, "https://x/y.tar.gz", None),
+            (
+                "https://x/y.tar.gz -> y-1.0.tar.gz",
+                None,
+                "https://x/y.tar.gz",
+                Some("y-1.0.tar.gz"),
+            ),
+            (
+                "mirror+https://x/y.tar.gz",
+                Some(Prefix::Mirror),
+                "https://x/y.tar.gz",
+                None,
+            ),
+            (
+                "fetch+mirror://gentoo/foo",
+                Some(Prefix::Fetch),
+                "mirror://gentoo/foo",
+                None,
+            ),
+            ("foo-1.0.tar.gz", None, "foo-1.0.tar.gz", None),
+        ];
+
+        for (input, prefix, expected, rename) in table {
+            let (rest, uri) = self::uri(input).unwrap();
+
+            assert!(rest.is_empty(), "{input}");
+            assert_eq!(uri.prefix(), prefix, "{input}");
+            assert_eq!(uri.uri(), expected, "{input}");
+            assert_eq!(uri.rename(), rename, "{input}");
+        }
+
+        for input in [")", "->", "foo?", "fetch+foo.tar.gz"] {
+            assert!(self::uri(input).is_err(), "{input}");
+        }
+
+        assert!(self::uri("https://x/y -> a/b").is_err());
+    }
+
+    #[test]
+    fn test_src_uri() {
+        let input = "https://a/a.tar.gz foo? ( https://b/b.tar.gz -> b-1.tar.gz !bar? ( mirror://gentoo/c ) ) ( https://d/d )";
+
+        let (rest, src_uri) = src_uri(input).unwrap();
+
+        assert!(rest.is_empty());
+
+        assert_eq!(src_uri.exprs().len(), 3);
+
+        assert!(matches!(
+            src_uri.exprs().nth(1),
+            Some(Expr::Conditional(_, exprs))
+            if
+            matches!(exprs[0], Expr::Uri(_)) &&
+            matches!(exprs[1], Expr::Conditional(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_eapi_src_uri() {
+        let table = [
+            ("https://a/a foo? ( https://b/b )", Eapi::Zero),
+            ("foo? ( https://a/a -> b )", Eapi::Two),
+            ("( fetch+https://a/a )", Eapi::Eight),
+        ];
+
+        for (input, since) in table {
+            for eapi in Eapi::ALL {
+                assert_eq!(
+                    eapi_src_uri(eapi)(input).is_ok_and(|(rest, _)| rest.is_empty()),
+                    eapi >= since,
+                    "{input} in EAPI {eapi}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use std::collections::HashSet;
+
+use crate::useflag::UseFlag;
+
+pub(crate) fn useflags(flags: &[&str]) -> HashSet<UseFlag> {
+    flags.iter().map(|flag| flag.parse().unwrap()).collect()
+}
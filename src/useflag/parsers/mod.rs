@@ -6,23 +6,27 @@ use nom::{
 };
 
 use crate::{
-    parser_utils::take_1_if,
+    error::Expected,
+    parser_utils::{context, take_1_if},
     useflag::{Operator, Sign},
     ParseResult,
 };
 
 use super::{Negate, UseDep, UseFlag};
 
-pub fn useflag(input: &str) -> ParseResult<UseFlag> {
-    recognize((
-        take_1_if(|c: char| c.is_ascii_alphanumeric()),
-        take_while(|c: char| c.is_ascii_alphanumeric() || matches!(c, '+' | '_' | '@' | '-')),
-    ))
+pub fn useflag(input: &str) -> ParseResult<'_, UseFlag> {
+    context(
+        Expected::UseFlag,
+        recognize((
+            take_1_if(|c: char| c.is_ascii_alphanumeric()),
+            take_while(|c: char| c.is_ascii_alphanumeric() || matches!(c, '+' | '_' | '@' | '-')),
+        )),
+    )
     .map(|result: &str| UseFlag(result.to_string()))
     .parse_complete(input)
 }
 
-pub fn usedep(input: &str) -> ParseResult<UseDep> {
+pub fn usedep(input: &str) -> ParseResult<'_, UseDep> {
     let negate = alt((
         tag("-").map(|_| Negate::Minus),
         tag("!").map(|_| Negate::Exclamation),
@@ -38,9 +42,12 @@ pub fn usedep(input: &str) -> ParseResult<UseDep> {
         tag("?").map(|_| Operator::Question),
     ));
 
-    (opt(negate), useflag, opt(sign), opt(operator))
-        .map(|(negate, useflag, sign, operator)| UseDep(negate, useflag, sign, operator))
-        .parse_complete(input)
+    context(
+        Expected::UseDep,
+        (opt(negate), useflag, opt(sign), opt(operator)),
+    )
+    .map(|(negate, useflag, sign, operator)| UseDep(negate, useflag, sign, operator))
+    .parse_complete(input)
 }
 
 #[cfg(test)]
@@ -56,4 +63,24 @@ mod tests {
 
         assert!(useflag("1valid+_@-").is_ok());
     }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            "python_targets_python3_13"
+                .parse::<UseFlag>()
+                .unwrap()
+                .get(),
+            "python_targets_python3_13"
+        );
+
+        assert!("foo bar".parse::<UseFlag>().is_err());
+
+        assert_eq!(
+            "!foo(+)?".parse::<UseDep>().unwrap().to_string(),
+            "!foo(+)?"
+        );
+
+        assert!("foo?,".parse::<UseDep>().is_err());
+    }
 }
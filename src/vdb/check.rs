@@ -0,0 +1,437 @@
+use core::fmt::{self, Display};
+use std::{
+    fs::{self, File, Metadata},
+    io,
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+};
+
+use md5::{Digest, Md5};
+
+use super::{Content, Error, Mtime, Package};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileType {
+    File,
+    Dir,
+    Symlink,
+    Fifo,
+    Device,
+    Other,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    Missing,
+    WrongType {
+        expected: FileType,
+        actual: FileType,
+    },
+    Md5Mismatch {
+        expected: String,
+        actual: String,
+    },
+    MtimeChanged {
+        expected: Mtime,
+        actual: Mtime,
+    },
+    SymlinkTarget {
+        expected: PathBuf,
+        actual: PathBuf,
+    },
+    // the entry could not be inspected, e.g. for lack of permissions
+    Unreadable(io::ErrorKind),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    content: Content,
+    problem: Problem,
+}
+
+// verifies installed files against their CONTENTS entries, like qcheck
+#[derive(Clone, Debug)]
+pub struct Checker {
+    root: PathBuf,
+    config_protect: Vec<PathBuf>,
+}
+
+impl Issue {
+    pub fn content(&self) -> &Content {
+        &self.content
+    }
+
+    pub fn problem(&self) -> &Problem {
+        &self.problem
+    }
+}
+
+impl Checker {
+    // root is the filesystem the package was installed to, usually /
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            config_protect: Vec::new(),
+        }
+    }
+
+    // entries under these paths (CONFIG_PROTECT) are skipped, since they are
+    // expected to be edited after being installed
+    pub fn ignore_config_protect(mut self, paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.config_protect.extend(paths);
+        self
+    }
+
+    pub fn check_package(&self, package: &Package) -> Result<Vec<Issue>, Error> {
+        Ok(self.check(&package.contents()?))
+    }
+
+    // I/O errors are reported as a problem with the entry they happened on,
+    // so one unreadable file does not hide the rest
+    pub fn check(&self, contents: &[Content]) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        for content in contents {
+            if self
+                .config_protect
+                .iter()
+                .any(|protected| content.path().starts_with(protected))
+            {
+                continue;
+            }
+
+            let problems = self
+                .check_content(content)
+                .unwrap_or_else(|error| vec![Problem::Unreadable(error.kind())]);
+
+            issues.extend(problems.into_iter().map(|problem| Issue {
+                content: content.clone(),
+                problem,
+            }));
+        }
+
+        issues
+    }
+
+    fn check_content(&self, content: &Content) -> io::Result<Vec<Problem>> {
+        let path = self
+            .root
+            .join(content.path().strip_prefix("/").unwrap_or(content.path()));
+
+        // directories are followed, merged-usr systems replace /bin, /lib and
+        // /sbin with symlinks to the directories under /usr
+        let metadata = match content {
+            Content::Dir(_) => fs::metadata(&path),
+            _ => fs::symlink_metadata(&path),
+        };
+
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(vec![Problem::Missing])
+            }
+            Err(error) => return Err(error),
+        };
+
+        let expected = match content {
+            Content::Obj(_) => FileType::File,
+            Content::Dir(_) => FileType::Dir,
+            Content::Sym(_) => FileType::Symlink,
+            Content::Fif(_) => FileType::Fifo,
+            Content::Dev(_) => FileType::Device,
+        };
+
+        let actual = file_type(&metadata);
+
+        if expected != actual {
+            return Ok(vec![Problem::WrongType { expected, actual }]);
+        }
+
+        let mut problems = Vec::new();
+
+        match content {
+            Content::Obj(obj) => {
+                let actual = md5sum(&path)?;
+
+                if !actual.eq_ignore_ascii_case(&obj.md5) {
+                    problems.push(Problem::Md5Mismatch {
+                        expected: obj.md5.clone(),
+                        actual,
+                    });
+                }
+
+                problems.extend(check_mtime(obj.mtime, &metadata)?);
+            }
+            Content::Sym(sym) => {
+                let actual = fs::read_link(&path)?;
+
+                if actual != sym.dest {
+                    problems.push(Problem::SymlinkTarget {
+                        expected: sym.dest.clone(),
+                        actual,
+                    });
+                }
+
+                problems.extend(check_mtime(sym.mtime, &metadata)?);
+            }
+            Content::Dir(_) | Content::Fif(_) | Content::Dev(_) => (),
+        }
+
+        Ok(problems)
+    }
+}
+
+impl Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::File => write!(f, "file"),
+            Self::Dir => write!(f, "directory"),
+            Self::Symlink => write!(f, "symlink"),
+            Self::Fifo => write!(f, "fifo"),
+            Self::Device => write!(f, "device"),
+            Self::Other => write!(f, "special file"),
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "does not exist"),
+            Self::WrongType { expected, actual } => {
+                write!(f, "expected {} but found {}", expected, actual)
+            }
+            Self::Md5Mismatch { expected, actual } => {
+                write!(
+                    f,
+                    "MD5 mismatch, expected {} but found {}",
+                    expected, actual
+                )
+            }
+            Self::MtimeChanged { expected, actual } => {
+                write!(f, "mtime changed from {} to {}", expected, actual)
+            }
+            Self::SymlinkTarget { expected, actual } => write!(
+                f,
+                "points to {} instead of {}",
+                actual.display(),
+                expected.display()
+            ),
+            Self::Unreadable(kind) => write!(f, "can not be read: {}", kind),
+        }
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.content.path().display(), self.problem)
+    }
+}
+
+fn file_type(metadata: &Metadata) -> FileType {
+    let file_type = metadata.file_type();
+
+    if file_type.is_symlink() {
+        FileType::Symlink
+    } else if file_type.is_dir() {
+        FileType::Dir
+    } else if file_type.is_file() {
+        FileType::File
+    } else if file_type.is_fifo() {
+        FileType::Fifo
+    } else if file_type.is_block_device() || file_type.is_char_device() {
+        FileType::Device
+    } else {
+        FileType::Other
+    }
+}
+
+fn check_mtime(expected: Mtime, metadata: &Metadata) -> io::Result<Option<Problem>> {
+    let actual = Mtime::from(metadata.modified()?);
+
+    Ok((actual != expected).then_some(Problem::MtimeChanged { expected, actual }))
+}
+
+fn md5sum(path: &Path) -> io::Result<String> {
+    let mut md5 = Md5::new();
+
+    io::copy(&mut File::open(path)?, &mut md5)?;
+
+    Ok(format!("{:x}", md5.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{
+        os::unix,
+        time::{Duration, SystemTime},
+    };
+
+    use crate::vdb::{parsers::contents::contents, Dir, Fif, Obj, Sym};
+
+    use super::*;
+
+    const MTIME: u64 = 1700000000;
+
+    fn root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("gentoo-utils-{}-{}", name, std::process::id()));
+
+        let _ = fs::remove_dir_all(&root);
+
+        fs::create_dir_all(root.join("etc")).unwrap();
+        fs::create_dir_all(root.join("usr/bin")).unwrap();
+
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(MTIME);
+
+        for (path, data) in [("usr/bin/foo", "foo\n"), ("etc/foo.conf", "changed\n")] {
+            fs::write(root.join(path), data).unwrap();
+            File::options()
+                .write(true)
+                .open(root.join(path))
+                .unwrap()
+                .set_modified(mtime)
+                .unwrap();
+        }
+
+        unix::fs::symlink("foo", root.join("usr/bin/bar")).unwrap();
+
+        root
+    }
+
+    #[test]
+    fn test_check() {
+        let root = root("check");
+
+        // md5 of "foo\n" and "bar\n"
+        let input = format!(
+            "dir /usr\n\
+             dir /usr/bin\n\
+             obj /usr/bin/foo d3b07384d113edec49eaa6238ad5ff00 {MTIME}\n\
+             obj /etc/foo.conf c157a79031e1c40f85931829bc5fc552 {MTIME}\n\
+             obj /usr/bin/missing d3b07384d113edec49eaa6238ad5ff00 {MTIME}\n\
+             obj /usr/bin/foo/bar d3b07384d113edec49eaa6238ad5ff00 {MTIME}\n\
+             dir /usr/bin/foo\n"
+        );
+
+        let (_, contents) = contents(&input).unwrap();
+
+        let issues = Checker::new(&root).check(&contents);
+
+        let problems = issues
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            problems,
+            [
+                "/etc/foo.conf: MD5 mismatch, expected c157a79031e1c40f85931829bc5fc552 but found ec1bebaea2c042beb68f7679ddd106a4",
+                "/usr/bin/missing: does not exist",
+                "/usr/bin/foo/bar: can not be read: not a directory",
+                "/usr/bin/foo: expected directory but found file",
+            ]
+        );
+
+        let issues = Checker::new(&root)
+            .ignore_config_protect(["/etc".into()])
+            .check(&contents);
+
+        assert_eq!(issues.len(), 3);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_check_symlink_and_mtime() {
+        let root = root("symlink");
+
+        let mtime = Mtime::from(
+            fs::symlink_metadata(root.join("usr/bin/bar"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+        );
+
+        let contents = [
+            Content::Sym(Sym {
+                src: "/usr/bin/bar".into(),
+                dest: "foo".into(),
+                mtime,
+            }),
+            Content::Sym(Sym {
+                src: "/usr/bin/bar".into(),
+                dest: "baz".into(),
+                mtime,
+            }),
+            Content::Obj(Obj {
+                path: "/usr/bin/foo".into(),
+                md5: "d3b07384d113edec49eaa6238ad5ff00".into(),
+                mtime: Mtime::from(MTIME + 1),
+            }),
+            Content::Dir(Dir {
+                path: "/usr/bin/bar".into(),
+            }),
+            Content::Fif(Fif {
+                path: "/usr/bin/foo".into(),
+            }),
+        ];
+
+        let problems = Checker::new(&root)
+            .check(&contents)
+            .into_iter()
+            .map(|issue| issue.problem)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            problems,
+            [
+                Problem::SymlinkTarget {
+                    expected: "baz".into(),
+                    actual: "foo".into()
+                },
+                Problem::MtimeChanged {
+                    expected: Mtime::from(MTIME + 1),
+                    actual: Mtime::from(MTIME)
+                },
+                Problem::WrongType {
+                    expected: FileType::Dir,
+                    actual: FileType::File
+                },
+                Problem::WrongType {
+                    expected: FileType::Fifo,
+                    actual: FileType::File
+                },
+            ]
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_check_directory_symlink() {
+        let root = root("merged-usr");
+
+        unix::fs::symlink("usr/bin", root.join("bin")).unwrap();
+        unix::fs::symlink("missing", root.join("lib")).unwrap();
+
+        let input = format!(
+            "dir /bin\n\
+             obj /bin/foo d3b07384d113edec49eaa6238ad5ff00 {MTIME}\n\
+             dir /lib\n"
+        );
+
+        let (_, contents) = contents(&input).unwrap();
+
+        let problems = Checker::new(&root)
+            .check(&contents)
+            .into_iter()
+            .map(|issue| issue.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(problems, ["/lib: does not exist"]);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
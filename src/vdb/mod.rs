@@ -1,29 +1,771 @@
-use std::path::PathBuf;
+use core::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
 
+use crate::{
+    atom::{self, Cpv, Repo, Slot},
+    depend::Dependencies,
+    eapi::Eapi,
+    parser_utils::parse_all,
+    useflag::UseFlag,
+};
+
+mod check;
+mod owners;
 pub mod parsers;
 
+pub use check::{Checker, FileType, Issue, Problem};
+pub use owners::{Index, Query};
+
+// the database of installed packages, usually /var/db/pkg
+#[derive(Clone, Debug)]
+pub struct Vdb {
+    root: PathBuf,
+}
+
+// an installed package, each accessor reads its file from the database when
+// called so nothing is loaded that is not asked for
+#[derive(Clone, Debug)]
+pub struct Package {
+    cpv: Cpv,
+    path: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse(crate::error::Error),
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Content {
     Obj(Obj),
     Dir(Dir),
     Sym(Sym),
+    Fif(Fif),
+    Dev(Dev),
 }
 
+// the modification time Portage records for files and symlinks, in seconds
+// since the unix epoch
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mtime(u64);
+
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Obj {
     pub path: PathBuf,
     pub md5: String,
-    pub size: u64,
+    pub mtime: Mtime,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dir {
     pub path: PathBuf,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sym {
     pub src: PathBuf,
     pub dest: PathBuf,
-    pub size: u64,
+    pub mtime: Mtime,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fif {
+    pub path: PathBuf,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dev {
+    pub path: PathBuf,
+}
+
+impl Content {
+    // the path the entry was installed to, for symlinks this is the link
+    // itself rather than its target
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Obj(obj) => obj.path.as_path(),
+            Self::Dir(dir) => dir.path.as_path(),
+            Self::Sym(sym) => sym.src.as_path(),
+            Self::Fif(fif) => fif.path.as_path(),
+            Self::Dev(dev) => dev.path.as_path(),
+        }
+    }
+}
+
+impl Mtime {
+    pub fn secs(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for Mtime {
+    fn from(secs: u64) -> Self {
+        Self(secs)
+    }
+}
+
+impl From<Mtime> for SystemTime {
+    fn from(mtime: Mtime) -> Self {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(mtime.0)
+    }
+}
+
+// times before the epoch can not be recorded in CONTENTS and come out as 0
+impl From<SystemTime> for Mtime {
+    fn from(time: SystemTime) -> Self {
+        Self(
+            time.duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+        )
+    }
+}
+
+impl Display for Mtime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// a single CONTENTS line the way Portage writes it, without the newline. this
+// does not check that the line can be parsed again, `write_contents` does
+impl Display for Content {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Obj(obj) => write!(f, "obj {} {} {}", obj.path.display(), obj.md5, obj.mtime),
+            Self::Dir(dir) => write!(f, "dir {}", dir.path.display()),
+            Self::Sym(sym) => write!(
+                f,
+                "sym {} -> {} {}",
+                sym.src.display(),
+                sym.dest.display(),
+                sym.mtime
+            ),
+            Self::Fif(fif) => write!(f, "fif {}", fif.path.display()),
+            Self::Dev(dev) => write!(f, "dev {}", dev.path.display()),
+        }
+    }
+}
+
+// parses a single CONTENTS line as written by `Display`, without the newline
+impl FromStr for Content {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(format!("{s}\n").as_str(), parsers::contents::content)
+    }
+}
+
+// writes a complete CONTENTS file, one newline terminated line per entry.
+// entries that could not be read back, such as paths containing a newline,
+// fail with io::ErrorKind::InvalidInput before anything is written
+pub fn write_contents<'a, W, I>(mut writer: W, contents: I) -> io::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = &'a Content>,
+{
+    let contents = contents.into_iter().collect::<Vec<_>>();
+
+    for content in &contents {
+        validate(content)?;
+    }
+
+    for content in contents {
+        writeln!(writer, "{}", content)?;
+    }
+
+    Ok(())
+}
+
+fn validate(content: &Content) -> io::Result<()> {
+    let invalid = |reason: &str| {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?}: {}", content.path(), reason),
+        ))
+    };
+
+    let paths = match content {
+        Content::Sym(sym) => vec![&sym.src, &sym.dest],
+        Content::Obj(Obj { path, .. })
+        | Content::Dir(Dir { path })
+        | Content::Fif(Fif { path })
+        | Content::Dev(Dev { path }) => vec![path],
+    };
+
+    // CONTENTS is read back as a string, so a path that is not valid UTF-8
+    // can not be written without losing bytes
+    let Some(paths) = paths
+        .iter()
+        .map(|path| path.to_str())
+        .collect::<Option<Vec<_>>>()
+    else {
+        return invalid("path is not valid UTF-8");
+    };
+
+    if paths.iter().any(|path| path.contains('\n')) {
+        return invalid("path contains a newline");
+    }
+
+    match content {
+        Content::Obj(obj)
+            if obj.md5.len() != 32 || !obj.md5.chars().all(|c| c.is_ascii_hexdigit()) =>
+        {
+            invalid("MD5 is not 32 hex digits")
+        }
+        Content::Sym(sym) if sym.src.to_string_lossy().contains(" -> ") => {
+            invalid("symlink path contains \" -> \"")
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Vdb {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        self.root.as_path()
+    }
+
+    // every installed package, sorted by cpv. entries that are not valid
+    // category/package-version names, such as the -MERGING- directories
+    // Portage leaves behind while merging, are skipped
+    pub fn packages(&self) -> Result<Vec<Package>, Error> {
+        let mut packages = Vec::new();
+
+        for category in fs::read_dir(&self.root)? {
+            let category = category?;
+
+            if !category.file_type()?.is_dir() {
+                continue;
+            }
+
+            for entry in fs::read_dir(category.path())? {
+                let entry = entry?;
+
+                if !entry.file_type()?.is_dir() {
+                    continue;
+                }
+
+                let cpv = format!(
+                    "{}/{}",
+                    category.file_name().to_string_lossy(),
+                    entry.file_name().to_string_lossy()
+                );
+
+                if let Ok(cpv) = cpv.parse::<Cpv>() {
+                    packages.push(Package {
+                        cpv,
+                        path: entry.path(),
+                    });
+                }
+            }
+        }
+
+        packages.sort_by(|a, b| a.cpv.cmp(&b.cpv));
+
+        Ok(packages)
+    }
+
+    pub fn package(&self, cpv: &Cpv) -> Option<Package> {
+        let path =
+            self.root
+                .join(cpv.category().get())
+                .join(format!("{}-{}", cpv.name(), cpv.version()));
+
+        path.is_dir().then(|| Package {
+            cpv: cpv.clone(),
+            path,
+        })
+    }
+}
+
+impl Package {
+    pub fn cpv(&self) -> &Cpv {
+        &self.cpv
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    pub fn slot(&self) -> Result<Option<Slot>, Error> {
+        self.parse("SLOT", |slot| slot.parse())
+    }
+
+    pub fn useflags(&self) -> Result<HashSet<UseFlag>, Error> {
+        Ok(self
+            .parse("USE", |flags| {
+                flags.split_whitespace().map(str::parse).collect()
+            })?
+            .unwrap_or_default())
+    }
+
+    // IUSE with the +/- defaults stripped
+    pub fn iuse(&self) -> Result<HashSet<UseFlag>, Error> {
+        Ok(self
+            .parse("IUSE", |flags| {
+                flags
+                    .split_whitespace()
+                    .map(|flag| flag.trim_start_matches(['+', '-']).parse())
+                    .collect()
+            })?
+            .unwrap_or_default())
+    }
+
+    pub fn keywords(&self) -> Result<Vec<String>, Error> {
+        Ok(self
+            .read("KEYWORDS")?
+            .map(|keywords| keywords.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default())
+    }
+
+    // packages without an EAPI file are EAPI 0
+    pub fn eapi(&self) -> Result<Eapi, Error> {
+        Ok(self.parse("EAPI", str::parse)?.unwrap_or(Eapi::Zero))
+    }
+
+    pub fn repository(&self) -> Result<Option<Repo>, Error> {
+        self.parse("repository", str::parse)
+    }
+
+    pub fn counter(&self) -> Result<Option<u64>, Error> {
+        self.parse("COUNTER", |counter| parse_all(counter, parsers::number))
+    }
+
+    pub fn build_time(&self) -> Result<Option<SystemTime>, Error> {
+        Ok(self
+            .parse("BUILD_TIME", |time| parse_all(time, parsers::number))?
+            .map(|time| SystemTime::UNIX_EPOCH + Duration::from_secs(time)))
+    }
+
+    // the size of the installed files in bytes
+    pub fn size(&self) -> Result<Option<u64>, Error> {
+        self.parse("SIZE", |size| parse_all(size, parsers::number))
+    }
+
+    pub fn dependencies(&self) -> Result<Dependencies, Error> {
+        Dependencies::from_vdb(&self.path)
+    }
+
+    pub fn contents(&self) -> Result<Vec<Content>, Error> {
+        Ok(self
+            .read("CONTENTS")?
+            .map(|contents| parse_all(&contents, parsers::contents::contents))
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    // the installed package in the form atoms are matched against
+    pub fn to_package(&self) -> Result<atom::Package, Error> {
+        let slot = self.slot()?;
+
+        Ok(atom::Package {
+            category: self.cpv.category().clone(),
+            name: self.cpv.name().clone(),
+            version: self.cpv.version().clone(),
+            slot: slot
+                .as_ref()
+                .and_then(Slot::primary)
+                .unwrap_or("0")
+                .to_string(),
+            subslot: slot.as_ref().and_then(Slot::sub).map(str::to_string),
+            repo: self.repository()?,
+            iuse: self.iuse()?,
+            useflags: self.useflags()?,
+        })
+    }
+
+    fn read(&self, name: &str) -> Result<Option<String>, Error> {
+        match fs::read_to_string(self.path.join(name)) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn parse<T>(
+        &self,
+        name: &str,
+        parse: impl FnOnce(&str) -> Result<T, crate::error::Error>,
+    ) -> Result<Option<T>, Error> {
+        Ok(self
+            .read(name)?
+            .map(|contents| parse(contents.trim()))
+            .transpose()?)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<crate::error::Error> for Error {
+    fn from(error: crate::error::Error) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Parse(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Parse(error) => Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    use super::*;
+
+    // a small xorshift generator, so the round trip test is reproducible
+    // without pulling in a property testing crate
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+
+        // paths with spaces, dots, digits and multi-byte characters in them,
+        // which are the cases the parser has to look ahead for
+        fn path(&mut self, absolute: bool) -> PathBuf {
+            const CHARS: &[char] = &[
+                'a', 'b', 'c', 'x', 'y', 'z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+                ' ', '.', '_', '-', '+', 'é', 'ß', 'ж', '日', '🦀',
+            ];
+
+            let mut path = String::new();
+
+            for i in 0..=self.below(4) {
+                if absolute || i > 0 {
+                    path.push('/');
+                }
+
+                for _ in 0..=self.below(12) {
+                    path.push(CHARS[self.below(CHARS.len() as u64) as usize]);
+                }
+            }
+
+            path.into()
+        }
+
+        fn content(&mut self) -> Content {
+            match self.below(5) {
+                0 => Content::Obj(Obj {
+                    path: self.path(true),
+                    md5: format!("{:016x}{:016x}", self.next(), self.next()),
+                    mtime: Mtime(self.below(1 << 40)),
+                }),
+                1 => Content::Dir(Dir {
+                    path: self.path(true),
+                }),
+                2 => {
+                    let absolute = self.below(2) == 0;
+
+                    Content::Sym(Sym {
+                        src: self.path(true),
+                        dest: self.path(absolute),
+                        mtime: Mtime(self.below(1 << 40)),
+                    })
+                }
+                3 => Content::Fif(Fif {
+                    path: self.path(true),
+                }),
+                _ => Content::Dev(Dev {
+                    path: self.path(true),
+                }),
+            }
+        }
+
+        fn with_newline(&mut self, path: &mut PathBuf) {
+            let mut s = path.to_string_lossy().into_owned();
+
+            let boundaries = s.char_indices().skip(1).map(|(i, _)| i).chain([s.len()]);
+            let at = boundaries
+                .clone()
+                .nth(self.below(boundaries.count() as u64) as usize)
+                .unwrap();
+
+            s.insert(at, '\n');
+
+            *path = s.into();
+        }
+
+        // entries CONTENTS can not represent
+        fn invalid_content(&mut self) -> Content {
+            let mut content = self.content();
+
+            match &mut content {
+                Content::Obj(obj) => match self.below(3) {
+                    0 => obj.md5.truncate(31),
+                    1 => obj.md5.replace_range(..1, "g"),
+                    _ => self.with_newline(&mut obj.path),
+                },
+                Content::Sym(sym) => match self.below(3) {
+                    0 => sym.src.push("a -> b"),
+                    1 => self.with_newline(&mut sym.src),
+                    _ => self.with_newline(&mut sym.dest),
+                },
+                Content::Dir(Dir { path })
+                | Content::Fif(Fif { path })
+                | Content::Dev(Dev { path }) => self.with_newline(path),
+            }
+
+            content
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let contents = [
+            Content::Dir(Dir {
+                path: "/usr/share/My Documents".into(),
+            }),
+            Content::Obj(Obj {
+                path: "/usr/share/My Documents/a file.txt".into(),
+                md5: "6c0d51586d94c272b160eb7ba6c61331".to_string(),
+                mtime: Mtime(1739589188),
+            }),
+            Content::Sym(Sym {
+                src: "/usr/lib/libfoo.so".into(),
+                dest: "libfoo.so.3".into(),
+                mtime: Mtime(1739589189),
+            }),
+            Content::Fif(Fif {
+                path: "/run/foo".into(),
+            }),
+            Content::Dev(Dev {
+                path: "/dev/foo".into(),
+            }),
+        ];
+
+        let mut buffer = Vec::new();
+
+        write_contents(&mut buffer, &contents).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "dir /usr/share/My Documents\n\
+             obj /usr/share/My Documents/a file.txt 6c0d51586d94c272b160eb7ba6c61331 1739589188\n\
+             sym /usr/lib/libfoo.so -> libfoo.so.3 1739589189\n\
+             fif /run/foo\n\
+             dev /dev/foo\n"
+        );
+
+        for content in &contents {
+            assert_eq!(&content.to_string().parse::<Content>().unwrap(), content);
+        }
+
+        for line in [
+            "dir /usr/share/doc/café",
+            "obj /usr/share/locale/日本/ß.mo 6c0d51586d94c272b160eb7ba6c61331 1739589188",
+            "sym /usr/lib/жж -> 🦀 1739589188",
+        ] {
+            assert_eq!(line.parse::<Content>().unwrap().to_string(), line);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+
+        for _ in 0..500 {
+            let contents = (0..rng.below(16))
+                .map(|_| rng.content())
+                .collect::<Vec<_>>();
+
+            let mut buffer = Vec::new();
+
+            write_contents(&mut buffer, &contents).unwrap();
+
+            let written = String::from_utf8(buffer).unwrap();
+
+            assert_eq!(
+                parse_all(&written, parsers::contents::contents).unwrap(),
+                contents,
+                "{written}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_invalid() {
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+
+        for _ in 0..500 {
+            let mut contents = (0..rng.below(16))
+                .map(|_| rng.content())
+                .collect::<Vec<_>>();
+
+            let invalid = rng.invalid_content();
+
+            contents.insert(
+                rng.below(contents.len() as u64 + 1) as usize,
+                invalid.clone(),
+            );
+
+            let mut buffer = Vec::new();
+
+            let error = write_contents(&mut buffer, &contents).unwrap_err();
+
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{invalid:?}");
+
+            assert!(buffer.is_empty());
+        }
+
+        let content = Content::Dir(Dir {
+            path: PathBuf::from(OsStr::from_bytes(b"/usr/share/caf\xe9")),
+        });
+
+        let error = write_contents(&mut Vec::new(), &[content]).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    fn vdb() -> Vdb {
+        Vdb::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/vdb"))
+    }
+
+    #[test]
+    fn test_packages() {
+        let packages = vdb()
+            .packages()
+            .unwrap()
+            .iter()
+            .map(|package| package.cpv().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            packages,
+            ["app-misc/hello-2.12.1-r1", "dev-lang/python-3.12.7"]
+        );
+    }
+
+    #[test]
+    fn test_package() {
+        let package = vdb()
+            .package(&"dev-lang/python-3.12.7".parse().unwrap())
+            .unwrap();
+
+        let slot = package.slot().unwrap().unwrap();
+
+        assert_eq!((slot.primary(), slot.sub()), (Some("3.12"), None));
+
+        assert!(package
+            .useflags()
+            .unwrap()
+            .contains(&"ssl".parse().unwrap()));
+
+        assert!(package
+            .iuse()
+            .unwrap()
+            .contains(&"ensurepip".parse().unwrap()));
+
+        assert_eq!(package.keywords().unwrap(), ["amd64", "~arm64"]);
+
+        assert_eq!(package.eapi().unwrap(), Eapi::Eight);
+
+        assert_eq!(package.repository().unwrap().unwrap().to_string(), "gentoo");
+
+        assert_eq!(package.counter().unwrap(), Some(3517));
+
+        assert_eq!(
+            package.build_time().unwrap(),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1729870000))
+        );
+
+        assert_eq!(package.size().unwrap(), Some(102400));
+
+        assert!(package
+            .dependencies()
+            .unwrap()
+            .build_time_atoms()
+            .any(|atom| atom.to_string() == "virtual/pkgconfig"));
+
+        let contents = package.contents().unwrap();
+
+        assert_eq!(contents.len(), 6);
+
+        assert!(matches!(
+            &contents[2],
+            Content::Obj(obj) if SystemTime::from(obj.mtime) == SystemTime::UNIX_EPOCH + Duration::from_secs(1729869990)
+        ));
+
+        let package = package.to_package().unwrap();
+
+        assert_eq!(package.slot, "3.12");
+
+        assert!("dev-lang/python:3.12[ssl]"
+            .parse::<atom::Atom>()
+            .unwrap()
+            .matches(&package));
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let package = vdb()
+            .package(&"app-misc/hello-2.12.1-r1".parse().unwrap())
+            .unwrap();
+
+        assert_eq!(package.eapi().unwrap(), Eapi::Zero);
+
+        assert!(package.repository().unwrap().is_none());
+
+        assert!(package.useflags().unwrap().is_empty());
+
+        assert!(package
+            .dependencies()
+            .unwrap()
+            .get(crate::depend::Class::Depend)
+            .is_empty());
+
+        assert_eq!(
+            package.contents().unwrap()[2].path(),
+            Path::new("/usr/share/doc/hello-2.12.1-r1/café.txt")
+        );
+
+        assert!(vdb()
+            .package(&"app-misc/hello-1".parse().unwrap())
+            .is_none());
+    }
 }
@@ -0,0 +1,195 @@
+use core::ops::Bound;
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use super::{Content, Error, Package, Vdb};
+
+// how a path from CONTENTS is matched, prefixes match whole components so
+// that /usr/lib does not match /usr/lib64
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Query {
+    Exact(PathBuf),
+    Basename(OsString),
+    Prefix(PathBuf),
+}
+
+// a reverse index from paths to the installed packages that own them, for
+// answering many queries without reading every CONTENTS file each time
+#[derive(Clone, Debug, Default)]
+pub struct Index {
+    packages: Vec<Package>,
+    contents: Vec<(usize, Content)>,
+    by_path: BTreeMap<PathBuf, Vec<usize>>,
+    by_basename: HashMap<OsString, Vec<usize>>,
+}
+
+impl Query {
+    pub fn matches(&self, path: &Path) -> bool {
+        match self {
+            Self::Exact(exact) => path == exact,
+            Self::Basename(basename) => path.file_name() == Some(basename.as_os_str()),
+            Self::Prefix(prefix) => path.starts_with(prefix),
+        }
+    }
+}
+
+impl Vdb {
+    // reads the CONTENTS of every installed package and returns the entries
+    // that match, use `index` instead when running many queries
+    pub fn owners(&self, query: &Query) -> Result<Vec<(Package, Content)>, Error> {
+        let mut owners = Vec::new();
+
+        for package in self.packages()? {
+            for content in package.contents()? {
+                if query.matches(content.path()) {
+                    owners.push((package.clone(), content));
+                }
+            }
+        }
+
+        Ok(owners)
+    }
+
+    pub fn index(&self) -> Result<Index, Error> {
+        let mut index = Index::default();
+
+        for package in self.packages()? {
+            let contents = package.contents()?;
+
+            index.insert(package, contents);
+        }
+
+        Ok(index)
+    }
+}
+
+impl Index {
+    pub fn insert(&mut self, package: Package, contents: Vec<Content>) {
+        let package_index = self.packages.len();
+
+        self.packages.push(package);
+
+        for content in contents {
+            let content_index = self.contents.len();
+            let path = content.path();
+
+            if let Some(basename) = path.file_name() {
+                self.by_basename
+                    .entry(basename.to_os_string())
+                    .or_default()
+                    .push(content_index);
+            }
+
+            self.by_path
+                .entry(path.to_path_buf())
+                .or_default()
+                .push(content_index);
+
+            self.contents.push((package_index, content));
+        }
+    }
+
+    pub fn owners(&self, query: &Query) -> Vec<(&Package, &Content)> {
+        let indices = match query {
+            Query::Exact(path) => self.by_path.get(path).cloned().unwrap_or_default(),
+            Query::Basename(basename) => {
+                self.by_basename.get(basename).cloned().unwrap_or_default()
+            }
+            // paths are ordered by component, so everything under the prefix
+            // directly follows it
+            Query::Prefix(prefix) => self
+                .by_path
+                .range::<Path, _>((Bound::Included(prefix.as_path()), Bound::Unbounded))
+                .take_while(|(path, _)| path.starts_with(prefix))
+                .flat_map(|(_, indices)| indices.iter().copied())
+                .collect(),
+        };
+
+        indices
+            .into_iter()
+            .map(|index| {
+                let (package, content) = &self.contents[index];
+
+                (&self.packages[*package], content)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn vdb() -> Vdb {
+        Vdb::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/vdb"))
+    }
+
+    #[test]
+    fn test_owners() {
+        let vdb = vdb();
+        let index = vdb.index().unwrap();
+
+        let table = [
+            (
+                Query::Exact("/usr".into()),
+                &[
+                    ("app-misc/hello-2.12.1-r1", "/usr"),
+                    ("dev-lang/python-3.12.7", "/usr"),
+                ][..],
+            ),
+            (
+                Query::Basename("python3".into()),
+                &[("dev-lang/python-3.12.7", "/usr/bin/python3")][..],
+            ),
+            (
+                Query::Prefix("/usr/bin".into()),
+                &[
+                    ("dev-lang/python-3.12.7", "/usr/bin"),
+                    ("app-misc/hello-2.12.1-r1", "/usr/bin/hello"),
+                    ("dev-lang/python-3.12.7", "/usr/bin/python3"),
+                    ("dev-lang/python-3.12.7", "/usr/bin/python3.12"),
+                ][..],
+            ),
+            (
+                Query::Basename("café.txt".into()),
+                &[(
+                    "app-misc/hello-2.12.1-r1",
+                    "/usr/share/doc/hello-2.12.1-r1/café.txt",
+                )][..],
+            ),
+            (Query::Prefix("/usr/bi".into()), &[][..]),
+            (Query::Exact("/nonexistent".into()), &[][..]),
+        ];
+
+        for (query, expected) in table {
+            let mut expected = expected
+                .iter()
+                .map(|(cpv, path)| (cpv.to_string(), PathBuf::from(path)))
+                .collect::<Vec<_>>();
+
+            let indexed = index
+                .owners(&query)
+                .into_iter()
+                .map(|(package, content)| (package.cpv().to_string(), content.path().to_path_buf()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(indexed, expected, "{query:?}");
+
+            let mut scanned = vdb
+                .owners(&query)
+                .unwrap()
+                .into_iter()
+                .map(|(package, content)| (package.cpv().to_string(), content.path().to_path_buf()))
+                .collect::<Vec<_>>();
+
+            scanned.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+            expected.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+            assert_eq!(scanned, expected, "{query:?}");
+        }
+    }
+}
@@ -3,29 +3,36 @@ use core::str;
 use nom::{AsChar, Parser};
 
 use crate::{
-    parser_utils::lookahead,
-    vdb::{Content, Dir, Obj, Sym},
+    error::{Expected, ParseError},
+    parser_utils::{context, lookahead},
+    vdb::{Content, Dev, Dir, Fif, Mtime, Obj, Sym},
     ParseResult,
 };
 
-#[derive(Clone, Debug)]
-pub enum Error<'a> {
-    Failure(&'a str),
-    Incomplete,
-}
+pub fn contents(input: &str) -> ParseResult<'_, Vec<Content>> {
+    use nom::multi::many0;
 
-pub fn contents(input: &str) -> ParseResult<Vec<Content>> {
-    use nom::{branch::alt, multi::many0};
+    many0(content).parse(input)
+}
 
-    many0(alt((
-        obj.map(Content::Obj),
-        dir.map(Content::Dir),
-        sym.map(Content::Sym),
-    )))
+// a single newline terminated CONTENTS line
+pub fn content(input: &str) -> ParseResult<'_, Content> {
+    use nom::branch::alt;
+
+    context(
+        Expected::Content,
+        alt((
+            obj.map(Content::Obj),
+            dir.map(Content::Dir),
+            sym.map(Content::Sym),
+            fif.map(Content::Fif),
+            dev.map(Content::Dev),
+        )),
+    )
     .parse(input)
 }
 
-fn md5(input: &str) -> ParseResult<String> {
+fn md5(input: &str) -> ParseResult<'_, String> {
     use nom::bytes::complete::take_while_m_n;
 
     take_while_m_n(32, 32, |c: char| c.is_hex_digit())
@@ -33,31 +40,31 @@ fn md5(input: &str) -> ParseResult<String> {
         .parse(input)
 }
 
-fn size(input: &str) -> ParseResult<u64> {
+fn mtime(input: &str) -> ParseResult<'_, Mtime> {
     use nom::bytes::take_while1;
 
     take_while1(|c: char| c.is_ascii_digit())
-        .map(|input: &str| input.parse::<u64>().unwrap())
+        .map_res(|input: &str| input.parse::<u64>().map(Mtime))
         .parse(input)
 }
 
-fn obj(input: &str) -> ParseResult<Obj> {
+fn obj(input: &str) -> ParseResult<'_, Obj> {
     use nom::{bytes::complete::tag, sequence::terminated};
 
-    let path = lookahead((tag(" "), md5, tag(" "), size, tag("\n")))
+    let path = lookahead((tag(" "), md5, tag(" "), mtime, tag("\n")))
         .map(|result: &str| result.to_string().into());
 
     (
         terminated(tag("obj"), tag(" ")),
         terminated(path, tag(" ")),
         terminated(md5, tag(" ")),
-        terminated(size, tag("\n")),
+        terminated(mtime, tag("\n")),
     )
-        .map(|(_, path, md5, size)| Obj { path, md5, size })
+        .map(|(_, path, md5, mtime)| Obj { path, md5, mtime })
         .parse(input)
 }
 
-fn dir(input: &str) -> ParseResult<Dir> {
+fn dir(input: &str) -> ParseResult<'_, Dir> {
     use nom::{bytes::complete::tag, sequence::terminated};
 
     let path = lookahead(tag("\n")).map(|result: &str| result.to_string().into());
@@ -70,22 +77,48 @@ fn dir(input: &str) -> ParseResult<Dir> {
         .parse(input)
 }
 
-fn sym(input: &str) -> ParseResult<Sym> {
+fn fif(input: &str) -> ParseResult<'_, Fif> {
+    use nom::{bytes::complete::tag, sequence::terminated};
+
+    let path = lookahead(tag("\n")).map(|result: &str| result.to_string().into());
+
+    (
+        terminated(tag("fif"), tag(" ")),
+        terminated(path, tag("\n")),
+    )
+        .map(|(_, path)| Fif { path })
+        .parse(input)
+}
+
+fn dev(input: &str) -> ParseResult<'_, Dev> {
+    use nom::{bytes::complete::tag, sequence::terminated};
+
+    let path = lookahead(tag("\n")).map(|result: &str| result.to_string().into());
+
+    (
+        terminated(tag("dev"), tag(" ")),
+        terminated(path, tag("\n")),
+    )
+        .map(|(_, path)| Dev { path })
+        .parse(input)
+}
+
+fn sym(input: &str) -> ParseResult<'_, Sym> {
     use nom::{bytes::complete::tag, sequence::terminated};
 
     let dest =
-        || lookahead((tag(" "), size, tag("\n"))).map(|result: &str| result.to_string().into());
+        || lookahead((tag(" "), mtime, tag("\n"))).map(|result: &str| result.to_string().into());
 
-    let src = lookahead((tag(" "), dest(), tag(" "), size, tag("\n")))
+    let src = lookahead((tag(" -> "), dest(), tag(" "), mtime, tag("\n")))
         .map(|result: &str| result.to_string().into());
 
     (
-        terminated(tag::<&str, &str, nom::error::Error<&str>>("sym"), tag(" ")),
+        terminated(tag::<&str, &str, ParseError>("sym"), tag(" ")),
         terminated(src, tag(" -> ")),
         terminated(dest(), tag(" ")),
-        terminated(size, tag("\n")),
+        terminated(mtime, tag("\n")),
     )
-        .map(|(_, src, dest, size)| Sym { src, dest, size })
+        .map(|(_, src, dest, mtime)| Sym { src, dest, mtime })
         .parse(input)
 }
 
@@ -98,7 +131,7 @@ mod tests {
 
     #[test]
     fn test_contents() {
-        let input = "obj /usr/share/alsa/ucm2/NXP/iMX8/Librem_5_Devkit/Librem 5 Devkit.conf 6c0d51586d94c272b160eb7ba6c61331 1739589188\ndir /a/path to something\nsym /a/path to something -> ../another path 102021\n";
+        let input = "obj /usr/share/alsa/ucm2/NXP/iMX8/Librem_5_Devkit/Librem 5 Devkit.conf 6c0d51586d94c272b160eb7ba6c61331 1739589188\ndir /a/path to something\nsym /a/path to something -> ../another path 102021\nfif /run/a fifo\ndev /dev/null\n";
 
         let expected = [
             Content::Obj(Obj {
@@ -106,7 +139,7 @@ mod tests {
                     "/usr/share/alsa/ucm2/NXP/iMX8/Librem_5_Devkit/Librem 5 Devkit.conf",
                 ),
                 md5: String::from("6c0d51586d94c272b160eb7ba6c61331"),
-                size: 1739589188,
+                mtime: Mtime(1739589188),
             }),
             Content::Dir(Dir {
                 path: PathBuf::from("/a/path to something"),
@@ -114,14 +147,20 @@ mod tests {
             Content::Sym(Sym {
                 src: PathBuf::from("/a/path to something"),
                 dest: PathBuf::from("../another path"),
-                size: 102021,
+                mtime: Mtime(102021),
+            }),
+            Content::Fif(Fif {
+                path: PathBuf::from("/run/a fifo"),
+            }),
+            Content::Dev(Dev {
+                path: PathBuf::from("/dev/null"),
             }),
         ];
 
-        let (_, contents) = contents(input).unwrap();
+        let (rest, contents) = contents(input).unwrap();
+
+        assert_eq!(contents, expected);
 
-        for (received, expected) in contents.iter().zip(expected) {
-            assert_eq!(*received, expected);
-        }
+        assert!(rest.is_empty());
     }
 }
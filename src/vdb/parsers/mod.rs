@@ -1 +1,14 @@
 pub mod contents;
+
+use nom::{bytes::complete::take_while1, Parser};
+
+use crate::{error::Expected, parser_utils::context, ParseResult};
+
+// the integers stored in COUNTER, BUILD_TIME and SIZE
+pub fn number(input: &str) -> ParseResult<'_, u64> {
+    context(
+        Expected::Number,
+        take_while1(|c: char| c.is_ascii_digit()).map_res(str::parse),
+    )
+    .parse_complete(input)
+}